@@ -2,9 +2,26 @@ use cmake;
 extern crate pkg_config;
 
 fn main() {
-    let dst = cmake::Config::new("nativefiledialog-extended")
-        .define("NFD_BUILD_TESTS", "OFF")
-        .build();
+    println!("cargo:rustc-check-cfg=cfg(nfd_native)");
+    println!("cargo:rustc-check-cfg=cfg(nfd_fake_ffi)");
+    // With the "xdg-portal" feature, dialogs on Linux are shown by pure Rust code, so NFDe is not needed
+    if xdg_portal_enabled() && std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("linux") {
+        return;
+    }
+    println!("cargo:rustc-cfg=nfd_native");
+
+    // Miri cannot run C code, so NFDe is replaced by a stand-in written in Rust (src/ffi/fake.rs)
+    if fake_ffi_enabled() && std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("linux") {
+        println!("cargo:rustc-cfg=nfd_fake_ffi");
+        return;
+    }
+
+    let mut config = cmake::Config::new("nativefiledialog-extended");
+    config.define("NFD_BUILD_TESTS", "OFF");
+    if portal_enabled() {
+        config.define("NFD_PORTAL", "ON");
+    }
+    let dst = config.build();
 
     println!("cargo:rustc-link-search=native={}/lib", dst.display());
     println!("cargo:rustc-link-lib=static=nfd");
@@ -33,5 +50,24 @@ fn print_macos() {
 
 #[cfg(target_os = "linux")]
 fn print_linux() {
-    pkg_config::Config::new().probe("gtk+-3.0").unwrap();
+    if portal_enabled() {
+        pkg_config::Config::new().probe("dbus-1").unwrap();
+    } else {
+        pkg_config::Config::new().probe("gtk+-3.0").unwrap();
+    }
+}
+
+// The "portal" feature makes NFDe use the XDG Desktop Portal over D-Bus instead of GTK (only affects Linux)
+fn portal_enabled() -> bool {
+    std::env::var_os("CARGO_FEATURE_PORTAL").is_some()
+}
+
+fn xdg_portal_enabled() -> bool {
+    std::env::var_os("CARGO_FEATURE_XDG_PORTAL").is_some()
+}
+
+// Set by `cargo miri`, or with `RUSTFLAGS="--cfg nfd_fake_ffi"` to run the same tests without Miri
+fn fake_ffi_enabled() -> bool {
+    std::env::var_os("CARGO_CFG_MIRI").is_some()
+        || std::env::var_os("CARGO_CFG_NFD_FAKE_FFI").is_some()
 }
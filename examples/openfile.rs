@@ -9,8 +9,8 @@ fn main() -> Result<(), nfde::Error> {
     // You can also set a default path using .default_path(Path)
     let res = nfd
         .open_file()
-        .add_filter("Source code", "c,cpp,cc")?
-        .add_filter("Headers", "h,hpp")?
+        .add_filter(Filter::new("Source code", &["c", "cpp", "cc"])?)?
+        .add_filter(Filter::new("Headers", &["h", "hpp"])?)?
         .show();
 
     match res {
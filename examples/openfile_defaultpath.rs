@@ -1,10 +1,10 @@
 use nfde::*;
 
-fn main() -> Result<(), nfde::Error> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize NFD... NFD will be automatically deinitialized when this object is destroyed
     let nfd = Nfd::new()?;
 
-    let current_exe_path = std::env::current_exe().map_err(|_| "Cannot get current exe path")?;
+    let current_exe_path = std::env::current_exe()?;
 
     // Show the dialog...
     // Note: .show() will block until the dialog is closed
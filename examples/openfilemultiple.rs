@@ -9,8 +9,8 @@ fn main() -> Result<(), nfde::Error> {
     // You can also set a default path using .default_path(Path)
     let res = nfd
         .open_file_multiple()
-        .add_filter("Source code", "c,cpp,cc")?
-        .add_filter("Headers", "h,hpp")?
+        .add_filter(("Source code", "c,cpp,cc"))?
+        .add_filter(("Headers", "h,hpp"))?
         .show();
 
     match res {
@@ -0,0 +1,28 @@
+use nfde::*;
+
+fn main() -> Result<(), nfde::Error> {
+    // Initialize NFD... NFD will be automatically deinitialized when this object is destroyed
+    let nfd = Nfd::new()?;
+
+    // Show the dialog...
+    // Note: .show() will block until the dialog is closed
+    // You can also set a default path using .default_path(Path)
+    let res = nfd.pick_folder_multiple().show();
+
+    match res {
+        DialogResult::Ok(pathset_buf) => {
+            println!("Success!");
+            for (i, path_buf) in pathset_buf.into_iter().enumerate() {
+                println!("Path {}: {}", i, path_buf?.display());
+            }
+        }
+        DialogResult::Cancel => {
+            println!("User pressed cancel.");
+        }
+        DialogResult::Err(error_str) => {
+            println!("Error: {}", error_str);
+        }
+    };
+
+    Ok(())
+}
@@ -10,8 +10,8 @@ fn main() -> Result<(), nfde::Error> {
     // and set a default name using .default_name(Path)
     let res = nfd
         .save_file()
-        .add_filter("Source code", "c,cpp,cc")?
-        .add_filter("Headers", "h,hpp")?
+        .add_filter(("Source code", "c,cpp,cc"))?
+        .add_filter(("Headers", "h,hpp"))?
         .show();
 
     match res {
@@ -0,0 +1,238 @@
+//! The interface between the dialog builders and whatever actually shows the dialogs.
+//!
+//! `Nfd::new()` tries the backends of `BackendKind::available()` in order, and uses the first one that can be initialized:
+//! `portal::PortalBackend` (Linux, `xdg-portal` feature), the native backend (NFDe),
+//! `subprocess::SubprocessBackend` (Linux, zenity or kdialog), and `tui::TuiBackend` (`tui` feature).
+//! `Nfd::builder()` can try them in another order.  Other backends, such as `mock::MockBackend`,
+//! can be installed with `Nfd::with_backend()`, and the dialog builders are used in the same way.
+
+use crate::Error;
+use crate::Filter;
+use crate::MultipleFileResult;
+use crate::MultipleFileSelection;
+use crate::SingleFileResult;
+use crate::SingleFileSelection;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+pub mod mock;
+#[cfg(nfd_native)]
+pub(crate) mod native;
+#[cfg(all(target_os = "linux", feature = "xdg-portal"))]
+pub mod portal;
+#[cfg(target_os = "linux")]
+pub mod subprocess;
+#[cfg(feature = "tui")]
+pub mod tui;
+
+/// A backend that `Nfd::new()` and `NfdBuilder` can try.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BackendKind {
+    /// NFDe, which uses GTK on Linux (or the XDG Desktop Portal with the `portal` feature).
+    Native,
+    /// `portal::PortalBackend`, with the `xdg-portal` feature on Linux.
+    XdgPortal,
+    /// `subprocess::SubprocessBackend`, with whichever of zenity and kdialog is installed, on Linux.
+    Subprocess,
+    /// `tui::TuiBackend`, with the `tui` feature.
+    Tui,
+}
+
+impl BackendKind {
+    /// Returns the backends that are built into this crate on this platform, in the order that `Nfd::new()` tries them.
+    pub fn available() -> Vec<BackendKind> {
+        [
+            BackendKind::XdgPortal,
+            BackendKind::Native,
+            BackendKind::Subprocess,
+            BackendKind::Tui,
+        ]
+        .iter()
+        .copied()
+        .filter(|kind| kind.is_available())
+        .collect()
+    }
+
+    /// Returns whether this backend is built into this crate on this platform.
+    pub fn is_available(self) -> bool {
+        match self {
+            BackendKind::Native => cfg!(nfd_native),
+            BackendKind::XdgPortal => cfg!(all(target_os = "linux", feature = "xdg-portal")),
+            BackendKind::Subprocess => cfg!(target_os = "linux"),
+            BackendKind::Tui => cfg!(feature = "tui"),
+        }
+    }
+
+    /// Creates the backend, without initializing it.
+    #[allow(unreachable_patterns)]
+    pub(crate) fn create(self) -> Result<Arc<dyn Backend>, Error> {
+        match self {
+            #[cfg(nfd_native)]
+            BackendKind::Native => Ok(Arc::new(native::NativeBackend)),
+            #[cfg(all(target_os = "linux", feature = "xdg-portal"))]
+            BackendKind::XdgPortal => Ok(Arc::new(portal::PortalBackend::new())),
+            #[cfg(target_os = "linux")]
+            BackendKind::Subprocess => match subprocess::SubprocessBackend::detect() {
+                Some(backend) => Ok(Arc::new(backend)),
+                None => Err(Error::Init(String::from(
+                    "Neither zenity nor kdialog was found on PATH",
+                ))),
+            },
+            #[cfg(feature = "tui")]
+            BackendKind::Tui => Ok(Arc::new(tui::TuiBackend::new())),
+            _ => Err(Error::Init(format!(
+                "The {} backend is not available in this build",
+                self
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BackendKind::Native => "native",
+            BackendKind::XdgPortal => "xdg-portal",
+            BackendKind::Subprocess => "subprocess",
+            BackendKind::Tui => "tui",
+        })
+    }
+}
+
+/// Which backend an `Nfd` chose, and why the backends tried before it were skipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackendSelection {
+    pub chosen: BackendKind,
+    /// The backends that failed to initialize, with their errors, in the order they were tried.
+    pub skipped: Vec<(BackendKind, Error)>,
+}
+
+/// The kind of dialog being shown.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DialogKind {
+    OpenFile,
+    OpenFileMultiple,
+    SaveFile,
+    PickFolder,
+    PickFolderMultiple,
+}
+
+impl DialogKind {
+    /// The title that the built-in backends give to dialogs of this kind.
+    pub fn title(self) -> &'static str {
+        match self {
+            DialogKind::OpenFile => "Open File",
+            DialogKind::OpenFileMultiple => "Open Files",
+            DialogKind::SaveFile => "Save File",
+            DialogKind::PickFolder => "Select Folder",
+            DialogKind::PickFolderMultiple => "Select Folders",
+        }
+    }
+}
+
+/// A window that a dialog should be modal to.
+///
+/// Backends ignore parent windows of the kinds used on other platforms.
+/// NFDe and `portal::PortalBackend` cannot use Wayland surfaces, so their dialogs fail with `Error::InvalidParentWindow`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ParentWindow {
+    /// A Win32 `HWND`.
+    Win32(isize),
+    /// An AppKit `NSView*`.
+    AppKit(usize),
+    /// An X11 window ID.
+    X11(u64),
+    /// A Wayland `wl_surface*`.
+    Wayland(usize),
+}
+
+#[cfg(feature = "raw-window-handle")]
+impl ParentWindow {
+    /// Gets the parent window from a window handle provided by a windowing library (e.g. winit).
+    pub fn from_window_handle<W: raw_window_handle::HasWindowHandle + ?Sized>(
+        window: &W,
+    ) -> Result<Self, Error> {
+        use raw_window_handle::RawWindowHandle;
+        let handle = window
+            .window_handle()
+            .map_err(|_| Error::InvalidParentWindow)?;
+        match handle.as_raw() {
+            RawWindowHandle::Win32(handle) => Ok(ParentWindow::Win32(handle.hwnd.get())),
+            RawWindowHandle::AppKit(handle) => {
+                Ok(ParentWindow::AppKit(handle.ns_view.as_ptr() as usize))
+            }
+            // c_ulong is narrower than u64 on some platforms
+            #[allow(clippy::useless_conversion)]
+            RawWindowHandle::Xlib(handle) => Ok(ParentWindow::X11(u64::from(handle.window))),
+            RawWindowHandle::Xcb(handle) => Ok(ParentWindow::X11(u64::from(handle.window.get()))),
+            RawWindowHandle::Wayland(handle) => {
+                Ok(ParentWindow::Wayland(handle.surface.as_ptr() as usize))
+            }
+            _ => Err(Error::InvalidParentWindow),
+        }
+    }
+}
+
+/// The options that a dialog builder passes to the backend.
+/// Options that do not apply to a kind of dialog are left empty.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DialogOptions<'a> {
+    /// The filters, in the order they were added.
+    pub filters: &'a [Filter],
+    /// The index into `filters` of the filter that should be selected when the dialog opens.
+    /// Always 0 if there are no filters.
+    pub default_filter: usize,
+    pub default_path: Option<&'a Path>,
+    pub default_name: Option<&'a Path>,
+    /// Whether a save dialog should ask before replacing an existing file.
+    /// Backends whose dialogs always ask may ignore this.
+    pub confirm_overwrite: bool,
+    pub parent_window: Option<ParentWindow>,
+}
+
+impl<'a> DialogOptions<'a> {
+    /// The filters with the default filter moved to the front, each with its index in `filters`,
+    /// for dialogs that always select the first filter.
+    pub(crate) fn filters_default_first(&self) -> Vec<(usize, &'a Filter)> {
+        let mut filters: Vec<(usize, &'a Filter)> = self.filters.iter().enumerate().collect();
+        if self.default_filter < filters.len() {
+            let default = filters.remove(self.default_filter);
+            filters.insert(0, default);
+        }
+        filters
+    }
+
+    /// Guesses the selected filter from the extensions of the selected paths, for dialogs that do not report it:
+    /// the default filter if all the paths match it, otherwise the first filter that all the paths match.
+    pub(crate) fn guess_filter_index<P: AsRef<Path>>(&self, paths: &[P]) -> Option<usize> {
+        self.filters_default_first()
+            .into_iter()
+            .find(|(_, filter)| paths.iter().all(|path| filter.matches(path)))
+            .map(|(index, _)| index)
+    }
+}
+
+/// Something that can show file dialogs.
+///
+/// A backend is shared by the `Nfd` that installed it and every builder created from that `Nfd`.
+pub trait Backend: Send + Sync {
+    /// Called when an `Nfd` using this backend is created on the current thread.
+    fn init(&self) -> Result<(), Error>;
+    /// Called when an `Nfd` using this backend is dropped.  Each successful `init()` is paired with one `quit()`.
+    fn quit(&self);
+
+    fn open_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection;
+    fn open_file_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileSelection;
+    fn save_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection;
+    fn pick_folder(&self, options: &DialogOptions<'_>) -> SingleFileResult;
+    fn pick_folder_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileResult;
+
+    /// Whether `show_async()` may show dialogs of this backend on a dedicated thread.
+    /// If not, `show_async()` fails with `Error::WrongThread`.  `true` by default.
+    fn supports_dialog_thread(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,199 @@
+//! A scripted backend for testing code that shows dialogs, without a display.
+//!
+//! Queue responses on a shared `MockBackend`, install it with `Nfd::with_backend()`,
+//! and use the dialog builders as usual.  Afterwards, `MockBackend::calls()` shows
+//! the filters, default path and default name that each builder passed.
+
+use super::Backend;
+use super::DialogKind;
+use super::DialogOptions;
+use crate::path;
+use crate::DialogResult;
+use crate::Error;
+use crate::Filter;
+use crate::MultipleFileResult;
+use crate::MultipleFileSelection;
+use crate::NfdPathSetBuf;
+use crate::Selection;
+use crate::SingleFileResult;
+use crate::SingleFileSelection;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+/// A scripted response to the next dialog shown with a `MockBackend`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MockResponse {
+    /// The user selected these paths.  Single-selection dialogs expect exactly one path.
+    /// The selected filter is reported as `None`.
+    Ok(Vec<PathBuf>),
+    /// The user selected these paths with the filter at this index selected.
+    OkWithFilter(Vec<PathBuf>, usize),
+    Cancel,
+    Err(Error),
+}
+
+/// A dialog shown with a `MockBackend`, with the options its builder passed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockCall {
+    pub kind: DialogKind,
+    pub filters: Vec<Filter>,
+    pub default_filter: usize,
+    pub default_path: Option<PathBuf>,
+    pub default_name: Option<PathBuf>,
+    pub confirm_overwrite: bool,
+}
+
+/// A backend that returns queued responses instead of showing dialogs,
+/// and records the options of every dialog it was asked to show.
+///
+/// If there is no queued response when a dialog is shown, the dialog fails with `Error::Dialog`.
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    state: Mutex<MockState>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: VecDeque<MockResponse>,
+    calls: Vec<MockCall>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a response for a later dialog.  Responses are used in the order they were queued.
+    pub fn push_response(&self, response: MockResponse) {
+        self.lock().responses.push_back(response);
+    }
+    /// Queues the selection of a single path.
+    pub fn push_ok<P: Into<PathBuf>>(&self, path: P) {
+        self.push_response(MockResponse::Ok(vec![path.into()]));
+    }
+    /// Queues the selection of multiple paths (for `open_file_multiple()` and `pick_folder_multiple()`).
+    pub fn push_ok_multiple<P: Into<PathBuf>, I: IntoIterator<Item = P>>(&self, paths: I) {
+        self.push_response(MockResponse::Ok(
+            paths.into_iter().map(Into::into).collect(),
+        ));
+    }
+    /// Queues the selection of a single path with the filter at `filter_index` selected.
+    pub fn push_ok_with_filter<P: Into<PathBuf>>(&self, path: P, filter_index: usize) {
+        self.push_response(MockResponse::OkWithFilter(vec![path.into()], filter_index));
+    }
+    pub fn push_cancel(&self) {
+        self.push_response(MockResponse::Cancel);
+    }
+    pub fn push_err(&self, error: Error) {
+        self.push_response(MockResponse::Err(error));
+    }
+
+    /// Returns the dialogs shown so far, in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.lock().calls.clone()
+    }
+    /// Returns the number of queued responses that have not been used yet.
+    pub fn pending_responses(&self) -> usize {
+        self.lock().responses.len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn respond(
+        &self,
+        kind: DialogKind,
+        options: &DialogOptions<'_>,
+    ) -> DialogResult<Selection<Vec<PathBuf>>> {
+        let mut state = self.lock();
+        state.calls.push(MockCall {
+            kind,
+            filters: options.filters.to_vec(),
+            default_filter: options.default_filter,
+            default_path: options.default_path.map(Into::into),
+            default_name: options.default_name.map(Into::into),
+            confirm_overwrite: options.confirm_overwrite,
+        });
+        match state.responses.pop_front() {
+            Some(MockResponse::Ok(paths)) => DialogResult::Ok(Selection {
+                value: paths,
+                filter_index: None,
+            }),
+            Some(MockResponse::OkWithFilter(paths, filter_index)) => DialogResult::Ok(Selection {
+                value: paths,
+                filter_index: Some(filter_index),
+            }),
+            Some(MockResponse::Cancel) => DialogResult::Cancel,
+            Some(MockResponse::Err(error)) => DialogResult::Err(error),
+            None => DialogResult::Err(Error::Dialog(String::from(
+                "MockBackend has no queued response",
+            ))),
+        }
+    }
+
+    fn respond_single(&self, kind: DialogKind, options: &DialogOptions<'_>) -> SingleFileSelection {
+        match self.respond(kind, options) {
+            DialogResult::Ok(selection) => {
+                let mut paths = selection.value.into_iter();
+                match (paths.next(), paths.next()) {
+                    (Some(path), None) => SingleFileSelection::Ok(Selection {
+                        value: path::owned_path(path),
+                        filter_index: selection.filter_index,
+                    }),
+                    _ => SingleFileSelection::Err(Error::Dialog(String::from(
+                        "MockBackend response must have exactly one path for this dialog",
+                    ))),
+                }
+            }
+            DialogResult::Cancel => SingleFileSelection::Cancel,
+            DialogResult::Err(error) => SingleFileSelection::Err(error),
+        }
+    }
+
+    fn respond_multiple(
+        &self,
+        kind: DialogKind,
+        options: &DialogOptions<'_>,
+    ) -> MultipleFileSelection {
+        self.respond(kind, options).map(|selection| Selection {
+            value: NfdPathSetBuf::from_paths(selection.value),
+            filter_index: selection.filter_index,
+        })
+    }
+}
+
+impl Backend for MockBackend {
+    fn init(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn quit(&self) {}
+
+    fn open_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        self.respond_single(DialogKind::OpenFile, options)
+    }
+
+    fn open_file_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileSelection {
+        self.respond_multiple(DialogKind::OpenFileMultiple, options)
+    }
+
+    fn save_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        self.respond_single(DialogKind::SaveFile, options)
+    }
+
+    fn pick_folder(&self, options: &DialogOptions<'_>) -> SingleFileResult {
+        self.respond_single(DialogKind::PickFolder, options)
+            .map(|selection| selection.value)
+    }
+
+    fn pick_folder_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileResult {
+        self.respond_multiple(DialogKind::PickFolderMultiple, options)
+            .map(|selection| selection.value)
+    }
+}
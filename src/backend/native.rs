@@ -0,0 +1,590 @@
+use super::Backend;
+use super::DialogOptions;
+use super::ParentWindow;
+use crate::ffi;
+use crate::get_nfd_error;
+use crate::native_filters::NativeFilters;
+use crate::path;
+use crate::DialogResult;
+use crate::Error;
+use crate::MultipleFileResult;
+use crate::MultipleFileSelection;
+use crate::NfdPathSetBuf;
+use crate::Selection;
+use crate::SingleFileResult;
+use crate::SingleFileSelection;
+use std::cell::Cell;
+use std::os::raw::c_void;
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+use std::path::PathBuf;
+#[cfg(target_os = "macos")]
+use std::sync::Mutex;
+#[cfg(target_os = "macos")]
+use std::thread::ThreadId;
+
+/// The backend that shows dialogs using NFDe.
+pub(crate) struct NativeBackend;
+
+thread_local! {
+    // Number of live `Nfd` handles using NFDe on this thread
+    static INIT_COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+// On Windows, NFDe initializes COM for each thread separately, so every thread may use it independently.
+#[cfg(target_os = "windows")]
+fn start() -> Result<(), Error> {
+    wrap_init_result(unsafe { ffi::NFD_Init() })
+}
+#[cfg(target_os = "windows")]
+fn stop() {
+    unsafe { ffi::NFD_Quit() };
+}
+
+// The thread that has initialized NFDe.  AppKit may only be used from the main thread,
+// so other threads cannot initialize NFDe until every `Nfd` of that thread is dropped.
+#[cfg(target_os = "macos")]
+static OWNER: Mutex<Option<ThreadId>> = Mutex::new(None);
+
+#[cfg(target_os = "macos")]
+fn start() -> Result<(), Error> {
+    let mut owner = match OWNER.lock() {
+        Ok(owner) => owner,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let current = std::thread::current().id();
+    if matches!(*owner, Some(thread) if thread != current) {
+        return Err(Error::WrongThread);
+    }
+    wrap_init_result(unsafe { ffi::NFD_Init() })?;
+    *owner = Some(current);
+    Ok(())
+}
+#[cfg(target_os = "macos")]
+fn stop() {
+    unsafe { ffi::NFD_Quit() };
+    match OWNER.lock() {
+        Ok(mut owner) => *owner = None,
+        Err(poisoned) => *poisoned.into_inner() = None,
+    }
+}
+
+// Elsewhere, NFDe is initialized on the NFDe thread while any thread holds an `Nfd`
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn start() -> Result<(), Error> {
+    nfd_thread::start(|| wrap_init_result(unsafe { ffi::NFD_Init() }))
+}
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn stop() {
+    nfd_thread::stop(|| unsafe { ffi::NFD_Quit() })
+}
+
+/// GTK (and the D-Bus connection of the portal) may only be used by one thread, which need not be the main thread.
+/// So every NFDe call is made on one long-lived thread, and any number of threads, including those of
+/// `show_async()`, can hold an `Nfd` and show dialogs.  The dialogs are shown one at a time.
+/// Path sets are copied on that thread before they are returned, since reading them also reads and clears
+/// the error of NFDe.  Only `NFD_FreePathN` is called elsewhere, by the thread that drops a single path,
+/// since it only frees the memory of the path.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod nfd_thread {
+    use crate::Error;
+    use std::sync::mpsc;
+    use std::sync::Mutex;
+    use std::sync::MutexGuard;
+
+    type Job = Box<dyn FnOnce() + Send>;
+
+    // Sends jobs to the NFDe thread, which is started by the first job
+    static JOBS: Mutex<Option<mpsc::Sender<Job>>> = Mutex::new(None);
+    // Number of threads holding an `Nfd` with NFDe
+    static USERS: Mutex<usize> = Mutex::new(0);
+
+    fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+        match mutex.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Runs `job` on the NFDe thread, and waits for it to finish.
+    pub(super) fn run<T, F>(job: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let jobs = lock(&JOBS)
+            .get_or_insert_with(|| {
+                let (jobs, receiver) = mpsc::channel::<Job>();
+                std::thread::spawn(move || {
+                    for job in receiver {
+                        job();
+                    }
+                });
+                jobs
+            })
+            .clone();
+        let (sender, result) = mpsc::sync_channel(1);
+        let stopped = || Error::Dialog(String::from("The NFDe thread has stopped"));
+        jobs.send(Box::new(move || {
+            let _ = sender.send(job());
+        }))
+        .map_err(|_| stopped())?;
+        result.recv().map_err(|_| stopped())
+    }
+
+    /// Runs `init` on the NFDe thread if no other thread holds an `Nfd` with NFDe.
+    pub(super) fn start<F>(init: F) -> Result<(), Error>
+    where
+        F: FnOnce() -> Result<(), Error> + Send + 'static,
+    {
+        // Held until NFDe is initialized, so that no other thread can use it before then
+        let mut users = lock(&USERS);
+        if *users == 0 {
+            run(init)??;
+        }
+        *users += 1;
+        Ok(())
+    }
+
+    /// Runs `quit` on the NFDe thread if no other thread holds an `Nfd` with NFDe.
+    pub(super) fn stop<F>(quit: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut users = lock(&USERS);
+        *users -= 1;
+        if *users == 0 {
+            let _ = run(quit);
+        }
+    }
+}
+
+/// Shows a dialog with NFDe on the thread that NFDe must be used from.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn show_dialog<T, F>(dialog: F) -> DialogResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> DialogResult<T> + Send + 'static,
+{
+    match nfd_thread::run(dialog) {
+        Ok(res) => res,
+        Err(error) => DialogResult::Err(error),
+    }
+}
+/// Shows a dialog with NFDe on the thread that NFDe must be used from, which is the calling thread.
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn show_dialog<T, F: FnOnce() -> DialogResult<T>>(dialog: F) -> DialogResult<T> {
+    dialog()
+}
+
+/// What a dialog returns from `show_dialog` for a path set.  On the NFDe thread, it is the paths copied out of the set,
+/// which cannot leave that thread.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+type ReturnedPathSet = Vec<PathBuf>;
+/// What a dialog returns from `show_dialog` for a path set, which is the set itself on the calling thread.
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+type ReturnedPathSet = NfdPathSetBuf;
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn return_path_set(pathset: NfdPathSetBuf) -> Result<ReturnedPathSet, Error> {
+    pathset.into_paths()
+}
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn receive_path_set(paths: ReturnedPathSet) -> NfdPathSetBuf {
+    NfdPathSetBuf::from_paths(paths)
+}
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn return_path_set(pathset: NfdPathSetBuf) -> Result<ReturnedPathSet, Error> {
+    Ok(pathset)
+}
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn receive_path_set(pathset: ReturnedPathSet) -> NfdPathSetBuf {
+    pathset
+}
+
+/// Dialogs may only be shown on a thread that currently holds an `Nfd`.
+fn check_initialized() -> Result<(), Error> {
+    if INIT_COUNT.with(|count| count.get()) == 0 {
+        Err(Error::NotInitialized)
+    } else {
+        Ok(())
+    }
+}
+
+impl Backend for NativeBackend {
+    fn init(&self) -> Result<(), Error> {
+        INIT_COUNT.with(|count| {
+            if count.get() == 0 {
+                start()?;
+            }
+            count.set(count.get() + 1);
+            Ok(())
+        })
+    }
+
+    fn quit(&self) {
+        INIT_COUNT.with(|count| {
+            count.set(count.get() - 1);
+            if count.get() == 0 {
+                stop();
+            }
+        });
+    }
+
+    fn open_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        if let Err(error) = check_initialized() {
+            return SingleFileSelection::Err(error);
+        }
+        let filters = match NativeFilters::new(options) {
+            Ok(filters) => filters,
+            Err(error) => return SingleFileSelection::Err(error),
+        };
+        let default_path = match options.default_path.map(path::unwrap_path).transpose() {
+            Ok(default_path) => default_path,
+            Err(error) => return SingleFileSelection::Err(error),
+        };
+        let parent_window = options.parent_window;
+        let res = show_dialog(move || {
+            // Points into `filters`, which outlives the call
+            let filter_items = filters.items();
+            let parent_window = match make_window_handle(parent_window) {
+                Ok(parent_window) => parent_window,
+                Err(error) => return SingleFileResult::Err(error),
+            };
+            let mut out: *mut ffi::nfdnchar_t = std::ptr::null_mut();
+            let args = ffi::nfdopendialognargs_t {
+                filterList: filter_items.as_ptr(),
+                filterCount: filter_items.len() as ffi::nfdfiltersize_t,
+                defaultPath: default_path
+                    .as_deref()
+                    .map_or_else(std::ptr::null, |p| p.as_ptr()),
+                parentWindow: parent_window,
+            };
+            let res = unsafe {
+                ffi::NFD_OpenDialogN_With_Impl(ffi::NFD_INTERFACE_VERSION, &mut out, &args)
+            };
+            wrap_single_file_result(res, out)
+        });
+        with_guessed_filter(options, res)
+    }
+
+    fn open_file_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileSelection {
+        if let Err(error) = check_initialized() {
+            return MultipleFileSelection::Err(error);
+        }
+        let filters = match NativeFilters::new(options) {
+            Ok(filters) => filters,
+            Err(error) => return MultipleFileSelection::Err(error),
+        };
+        let default_path = match options.default_path.map(path::unwrap_path).transpose() {
+            Ok(default_path) => default_path,
+            Err(error) => return MultipleFileSelection::Err(error),
+        };
+        let parent_window = options.parent_window;
+        let res = show_dialog(move || {
+            // Points into `filters`, which outlives the call
+            let filter_items = filters.items();
+            let parent_window = match make_window_handle(parent_window) {
+                Ok(parent_window) => parent_window,
+                Err(error) => return DialogResult::Err(error),
+            };
+            let mut out: *mut ffi::nfdpathset_t = std::ptr::null_mut();
+            let args = ffi::nfdopendialognargs_t {
+                filterList: filter_items.as_ptr(),
+                filterCount: filter_items.len() as ffi::nfdfiltersize_t,
+                defaultPath: default_path
+                    .as_deref()
+                    .map_or_else(std::ptr::null, |p| p.as_ptr()),
+                parentWindow: parent_window,
+            };
+            let res = unsafe {
+                ffi::NFD_OpenDialogMultipleN_With_Impl(ffi::NFD_INTERFACE_VERSION, &mut out, &args)
+            };
+            wrap_multiple_file_result(res, out)
+        });
+        res.map(|pathset| {
+            let pathset = receive_path_set(pathset);
+            // Only read the paths back if there is a filter to guess
+            let filter_index = if options.filters.is_empty() {
+                None
+            } else {
+                let paths: Vec<_> = pathset.iter().filter_map(Result::ok).collect();
+                options.guess_filter_index(&paths)
+            };
+            Selection {
+                filter_index,
+                value: pathset,
+            }
+        })
+    }
+
+    fn save_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        if let Err(error) = check_initialized() {
+            return SingleFileSelection::Err(error);
+        }
+        let filters = match NativeFilters::new(options) {
+            Ok(filters) => filters,
+            Err(error) => return SingleFileSelection::Err(error),
+        };
+        let default_path = match options.default_path.map(path::unwrap_path).transpose() {
+            Ok(default_path) => default_path,
+            Err(error) => return SingleFileSelection::Err(error),
+        };
+        let default_name = match options.default_name.map(path::unwrap_path).transpose() {
+            Ok(default_name) => default_name,
+            Err(error) => return SingleFileSelection::Err(error),
+        };
+        let parent_window = options.parent_window;
+        let res = show_dialog(move || {
+            // Points into `filters`, which outlives the call
+            let filter_items = filters.items();
+            let parent_window = match make_window_handle(parent_window) {
+                Ok(parent_window) => parent_window,
+                Err(error) => return SingleFileResult::Err(error),
+            };
+            let mut out: *mut ffi::nfdnchar_t = std::ptr::null_mut();
+            let args = ffi::nfdsavedialognargs_t {
+                filterList: filter_items.as_ptr(),
+                filterCount: filter_items.len() as ffi::nfdfiltersize_t,
+                defaultPath: default_path
+                    .as_deref()
+                    .map_or_else(std::ptr::null, |p| p.as_ptr()),
+                defaultName: default_name
+                    .as_deref()
+                    .map_or_else(std::ptr::null, |n| n.as_ptr()),
+                parentWindow: parent_window,
+            };
+            let res = unsafe {
+                ffi::NFD_SaveDialogN_With_Impl(ffi::NFD_INTERFACE_VERSION, &mut out, &args)
+            };
+            wrap_single_file_result(res, out)
+        });
+        with_guessed_filter(options, res)
+    }
+
+    fn pick_folder(&self, options: &DialogOptions<'_>) -> SingleFileResult {
+        if let Err(error) = check_initialized() {
+            return SingleFileResult::Err(error);
+        }
+        let default_path = match options.default_path.map(path::unwrap_path).transpose() {
+            Ok(default_path) => default_path,
+            Err(error) => return SingleFileResult::Err(error),
+        };
+        let parent_window = options.parent_window;
+        show_dialog(move || {
+            let parent_window = match make_window_handle(parent_window) {
+                Ok(parent_window) => parent_window,
+                Err(error) => return SingleFileResult::Err(error),
+            };
+            let mut out: *mut ffi::nfdnchar_t = std::ptr::null_mut();
+            let args = ffi::nfdpickfoldernargs_t {
+                defaultPath: default_path
+                    .as_deref()
+                    .map_or_else(std::ptr::null, |p| p.as_ptr()),
+                parentWindow: parent_window,
+            };
+            let res = unsafe {
+                ffi::NFD_PickFolderN_With_Impl(ffi::NFD_INTERFACE_VERSION, &mut out, &args)
+            };
+            wrap_single_file_result(res, out)
+        })
+    }
+
+    fn pick_folder_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileResult {
+        if let Err(error) = check_initialized() {
+            return MultipleFileResult::Err(error);
+        }
+        let default_path = match options.default_path.map(path::unwrap_path).transpose() {
+            Ok(default_path) => default_path,
+            Err(error) => return MultipleFileResult::Err(error),
+        };
+        let parent_window = options.parent_window;
+        let res = show_dialog(move || {
+            let parent_window = match make_window_handle(parent_window) {
+                Ok(parent_window) => parent_window,
+                Err(error) => return DialogResult::Err(error),
+            };
+            let mut out: *mut ffi::nfdpathset_t = std::ptr::null_mut();
+            let args = ffi::nfdpickfoldernargs_t {
+                defaultPath: default_path
+                    .as_deref()
+                    .map_or_else(std::ptr::null, |p| p.as_ptr()),
+                parentWindow: parent_window,
+            };
+            let res = unsafe {
+                ffi::NFD_PickFolderMultipleN_With_Impl(ffi::NFD_INTERFACE_VERSION, &mut out, &args)
+            };
+            wrap_multiple_file_result(res, out)
+        });
+        res.map(receive_path_set)
+    }
+
+    /// AppKit only allows dialogs on the main thread, so macOS cannot show dialogs on a dialog thread.
+    fn supports_dialog_thread(&self) -> bool {
+        !cfg!(target_os = "macos")
+    }
+}
+
+/// Converts the parent window to the kind of handle that NFDe uses on this platform.
+/// Wayland surfaces are rejected with `Error::InvalidParentWindow`, since the GTK implementation cannot use them
+/// and the dialog would silently not be modal.  The kinds of other platforms cannot occur, so they are dropped.
+#[allow(unreachable_patterns)]
+fn make_window_handle(
+    parent_window: Option<ParentWindow>,
+) -> Result<ffi::nfdwindowhandle_t, Error> {
+    Ok(match parent_window {
+        #[cfg(target_os = "windows")]
+        Some(ParentWindow::Win32(hwnd)) => ffi::nfdwindowhandle_t {
+            type_: ffi::NFD_WINDOW_HANDLE_TYPE_WINDOWS,
+            handle: hwnd as *mut c_void,
+        },
+        #[cfg(target_os = "macos")]
+        Some(ParentWindow::AppKit(ns_view)) => ffi::nfdwindowhandle_t {
+            type_: ffi::NFD_WINDOW_HANDLE_TYPE_COCOA,
+            handle: ns_view_window(ns_view as *mut c_void),
+        },
+        #[cfg(target_os = "linux")]
+        Some(ParentWindow::X11(window)) => ffi::nfdwindowhandle_t {
+            type_: ffi::NFD_WINDOW_HANDLE_TYPE_X11,
+            handle: window as usize as *mut c_void,
+        },
+        Some(ParentWindow::Wayland(_)) => return Err(Error::InvalidParentWindow),
+        _ => ffi::nfdwindowhandle_t {
+            type_: ffi::NFD_WINDOW_HANDLE_TYPE_UNSET,
+            handle: std::ptr::null_mut(),
+        },
+    })
+}
+
+/// NFDe wants the `NSWindow*`, but window handles give us the `NSView*`, so we call `[view window]`.
+#[cfg(target_os = "macos")]
+fn ns_view_window(ns_view: *mut c_void) -> *mut c_void {
+    use std::os::raw::c_char;
+    extern "C" {
+        fn sel_registerName(name: *const c_char) -> *mut c_void;
+        fn objc_msgSend();
+    }
+    unsafe {
+        let send: unsafe extern "C" fn(*mut c_void, *mut c_void) -> *mut c_void =
+            std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+        send(
+            ns_view,
+            sel_registerName(b"window\0".as_ptr() as *const c_char),
+        )
+    }
+}
+
+/// NFDe does not report the selected filter, so it is guessed from the extension.
+fn with_guessed_filter(options: &DialogOptions<'_>, res: SingleFileResult) -> SingleFileSelection {
+    res.map(|path| Selection {
+        filter_index: options.guess_filter_index(&[&path]),
+        value: path,
+    })
+}
+
+fn wrap_init_result(res: ffi::nfdresult_t) -> Result<(), Error> {
+    match res {
+        ffi::nfdresult_t::NFD_ERROR => Err(Error::Init(get_nfd_error())),
+        _ => Ok(()),
+    }
+}
+
+fn wrap_single_file_result(res: ffi::nfdresult_t, out: *mut ffi::nfdnchar_t) -> SingleFileResult {
+    match res {
+        ffi::nfdresult_t::NFD_ERROR => SingleFileResult::Err(Error::Dialog(get_nfd_error())),
+        ffi::nfdresult_t::NFD_OKAY => SingleFileResult::Ok(path::wrap_path(out)),
+        ffi::nfdresult_t::NFD_CANCEL => SingleFileResult::Cancel,
+    }
+}
+
+fn wrap_multiple_file_result(
+    res: ffi::nfdresult_t,
+    out: *mut ffi::nfdpathset_t,
+) -> DialogResult<ReturnedPathSet> {
+    match res {
+        ffi::nfdresult_t::NFD_ERROR => DialogResult::Err(Error::Dialog(get_nfd_error())),
+        ffi::nfdresult_t::NFD_OKAY => match NfdPathSetBuf::new(out).and_then(return_path_set) {
+            Ok(pathset) => DialogResult::Ok(pathset),
+            Err(error) => DialogResult::Err(error),
+        },
+        ffi::nfdresult_t::NFD_CANCEL => DialogResult::Cancel,
+    }
+}
+
+// Against the stand-in for NFDe, which fails dialogs that are not shown on the thread that initialized it
+#[cfg(all(test, nfd_fake_ffi))]
+mod tests {
+    use crate::backend::BackendKind;
+    use crate::DialogResult;
+    use crate::MultipleFileDialogBuilder;
+    use crate::Nfd;
+    use crate::SingleFileDialogBuilder;
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::sync::Barrier;
+    use std::task::Context;
+    use std::task::Poll;
+    use std::task::Wake;
+    use std::task::Waker;
+
+    struct ThreadWaker(std::thread::Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    // Waits for a future by parking the thread until it is woken
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    fn native_nfd() -> Nfd {
+        Nfd::builder()
+            .backends(vec![BackendKind::Native])
+            .init()
+            .unwrap()
+    }
+
+    #[test]
+    #[cfg_attr(
+        miri,
+        ignore = "the NFDe thread outlives the test, which Miri does not allow"
+    )]
+    fn show_async_shows_dialogs_on_the_nfde_thread() {
+        let nfd = native_nfd();
+        assert_eq!(block_on(nfd.open_file().show_async()), DialogResult::Cancel);
+        assert_eq!(
+            block_on(nfd.open_file_multiple().show_async()),
+            DialogResult::Cancel
+        );
+        assert_eq!(nfd.save_file().show(), DialogResult::Cancel);
+    }
+
+    #[test]
+    #[cfg_attr(
+        miri,
+        ignore = "the NFDe thread outlives the test, which Miri does not allow"
+    )]
+    fn threads_can_hold_nfd_at_the_same_time() {
+        let barrier = Barrier::new(2);
+        std::thread::scope(|scope| {
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    let nfd = native_nfd();
+                    // Both threads hold an `Nfd` while showing their dialogs
+                    barrier.wait();
+                    assert_eq!(nfd.pick_folder().show(), DialogResult::Cancel);
+                    barrier.wait();
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,406 @@
+//! A backend that shows dialogs with the XDG Desktop Portal (`org.freedesktop.portal.FileChooser`),
+//! talking to it over D-Bus in pure Rust, so that NFDe (and CMake and GTK) are not needed.
+
+use super::Backend;
+use super::DialogKind;
+use super::DialogOptions;
+use super::ParentWindow;
+use crate::path;
+use crate::DialogResult;
+use crate::Error;
+use crate::Filter;
+use crate::MultipleFileResult;
+use crate::MultipleFileSelection;
+use crate::NfdPathSetBuf;
+use crate::Selection;
+use crate::SingleFileResult;
+use crate::SingleFileSelection;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::ffi::OsStringExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use zbus::blocking::Connection;
+use zbus::blocking::Proxy;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::zvariant::OwnedValue;
+use zbus::zvariant::Value;
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const FILE_CHOOSER_INTERFACE: &str = "org.freedesktop.portal.FileChooser";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+// Used to make the handle tokens of our requests unique
+static NEXT_TOKEN: AtomicUsize = AtomicUsize::new(0);
+
+/// The backend that shows dialogs with the XDG Desktop Portal on the session bus.
+#[derive(Default)]
+pub struct PortalBackend {
+    connection: Mutex<Option<Connection>>,
+}
+
+impl PortalBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn connection(&self) -> Result<Connection, Error> {
+        match self.connection.lock() {
+            Ok(connection) => connection.clone().ok_or(Error::NotInitialized),
+            Err(poisoned) => poisoned.into_inner().clone().ok_or(Error::NotInitialized),
+        }
+    }
+
+    /// Asks the portal to show a dialog, and waits for the user to close it.
+    /// Returns the results of the request if the user made a selection.
+    fn request(
+        &self,
+        method: &str,
+        title: &str,
+        parent_window: Option<ParentWindow>,
+        mut options: HashMap<&str, Value<'_>>,
+    ) -> DialogResult<HashMap<String, OwnedValue>> {
+        let connection = match self.connection() {
+            Ok(connection) => connection,
+            Err(error) => return DialogResult::Err(error),
+        };
+        match request_on(&connection, method, title, parent_window, &mut options) {
+            Ok(Some(results)) => DialogResult::Ok(results),
+            Ok(None) => DialogResult::Cancel,
+            Err(error) => DialogResult::Err(error),
+        }
+    }
+
+    /// Shows a dialog, and returns the selected paths and filter.
+    fn show(
+        &self,
+        method: &str,
+        title: &str,
+        options: &DialogOptions<'_>,
+        portal_options: HashMap<&str, Value<'_>>,
+    ) -> DialogResult<Selection<Vec<PathBuf>>> {
+        match self.request(method, title, options.parent_window, portal_options) {
+            DialogResult::Ok(mut results) => {
+                let current_filter = results.remove("current_filter");
+                match selected_paths(results) {
+                    Ok(paths) => DialogResult::Ok(Selection {
+                        filter_index: selected_filter_index(options, current_filter, &paths),
+                        value: paths,
+                    }),
+                    Err(error) => DialogResult::Err(error),
+                }
+            }
+            DialogResult::Cancel => DialogResult::Cancel,
+            DialogResult::Err(error) => DialogResult::Err(error),
+        }
+    }
+
+    fn show_single(
+        &self,
+        method: &str,
+        title: &str,
+        options: &DialogOptions<'_>,
+        portal_options: HashMap<&str, Value<'_>>,
+    ) -> SingleFileSelection {
+        match self.show(method, title, options, portal_options) {
+            DialogResult::Ok(selection) => match selection.value.into_iter().next() {
+                Some(path) => SingleFileSelection::Ok(Selection {
+                    value: path::owned_path(path),
+                    filter_index: selection.filter_index,
+                }),
+                None => SingleFileSelection::Err(Error::Dialog(String::from(
+                    "The portal did not return a path",
+                ))),
+            },
+            DialogResult::Cancel => SingleFileSelection::Cancel,
+            DialogResult::Err(error) => SingleFileSelection::Err(error),
+        }
+    }
+
+    fn show_multiple(
+        &self,
+        title: &str,
+        options: &DialogOptions<'_>,
+        portal_options: HashMap<&str, Value<'_>>,
+    ) -> MultipleFileSelection {
+        self.show("OpenFile", title, options, portal_options)
+            .map(|selection| Selection {
+                value: NfdPathSetBuf::from_paths(selection.value),
+                filter_index: selection.filter_index,
+            })
+    }
+}
+
+impl Backend for PortalBackend {
+    fn init(&self) -> Result<(), Error> {
+        let mut connection = match self.connection.lock() {
+            Ok(connection) => connection,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if connection.is_none() {
+            let session = Connection::session().map_err(|e| Error::Init(e.to_string()))?;
+            // Make sure that some portal can actually show file dialogs
+            Proxy::new(
+                &session,
+                PORTAL_DESTINATION,
+                PORTAL_PATH,
+                FILE_CHOOSER_INTERFACE,
+            )
+            .and_then(|proxy| proxy.get_property::<u32>("version"))
+            .map_err(|e| Error::Init(format!("The file chooser portal is not available: {}", e)))?;
+            *connection = Some(session);
+        }
+        Ok(())
+    }
+
+    fn quit(&self) {
+        // the connection is kept for later `Nfd`s, and closed when the backend is dropped
+    }
+
+    fn open_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        let mut portal_options = HashMap::new();
+        add_filters(&mut portal_options, options);
+        add_current_folder(&mut portal_options, options.default_path);
+        self.show_single(
+            "OpenFile",
+            DialogKind::OpenFile.title(),
+            options,
+            portal_options,
+        )
+    }
+
+    fn open_file_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileSelection {
+        let mut portal_options = HashMap::new();
+        portal_options.insert("multiple", Value::from(true));
+        add_filters(&mut portal_options, options);
+        add_current_folder(&mut portal_options, options.default_path);
+        self.show_multiple(
+            DialogKind::OpenFileMultiple.title(),
+            options,
+            portal_options,
+        )
+    }
+
+    fn save_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        let mut portal_options = HashMap::new();
+        add_filters(&mut portal_options, options);
+        add_current_folder(&mut portal_options, options.default_path);
+        if let Some(default_name) = options.default_name {
+            portal_options.insert(
+                "current_name",
+                Value::from(default_name.to_string_lossy().into_owned()),
+            );
+        }
+        self.show_single(
+            "SaveFile",
+            DialogKind::SaveFile.title(),
+            options,
+            portal_options,
+        )
+    }
+
+    fn pick_folder(&self, options: &DialogOptions<'_>) -> SingleFileResult {
+        let mut portal_options = HashMap::new();
+        portal_options.insert("directory", Value::from(true));
+        add_current_folder(&mut portal_options, options.default_path);
+        self.show_single(
+            "OpenFile",
+            DialogKind::PickFolder.title(),
+            options,
+            portal_options,
+        )
+        .map(|selection| selection.value)
+    }
+
+    fn pick_folder_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileResult {
+        let mut portal_options = HashMap::new();
+        portal_options.insert("directory", Value::from(true));
+        portal_options.insert("multiple", Value::from(true));
+        add_current_folder(&mut portal_options, options.default_path);
+        self.show_multiple(
+            DialogKind::PickFolderMultiple.title(),
+            options,
+            portal_options,
+        )
+        .map(|selection| selection.value)
+    }
+}
+
+/// Calls the file chooser method, then waits for the `Response` signal of the request it creates.
+/// Returns `None` if the user cancelled the dialog.
+fn request_on(
+    connection: &Connection,
+    method: &str,
+    title: &str,
+    parent_window: Option<ParentWindow>,
+    options: &mut HashMap<&str, Value<'_>>,
+) -> Result<Option<HashMap<String, OwnedValue>>, Error> {
+    let parent_window = parent_window_identifier(parent_window)?;
+    let token = format!("nfde{}", NEXT_TOKEN.fetch_add(1, Ordering::Relaxed));
+    let sender = connection
+        .unique_name()
+        .ok_or_else(|| Error::Dialog(String::from("Not connected to the session bus")))?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    // Subscribe to the response before making the request, so that it cannot be missed
+    let request_path = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+    let mut responses = Proxy::new(
+        connection,
+        PORTAL_DESTINATION,
+        request_path.as_str(),
+        REQUEST_INTERFACE,
+    )
+    .and_then(|request| request.receive_signal("Response"))
+    .map_err(dialog_error)?;
+
+    options.insert("handle_token", Value::from(token));
+    options.insert("modal", Value::from(true));
+    let handle: OwnedObjectPath = Proxy::new(
+        connection,
+        PORTAL_DESTINATION,
+        PORTAL_PATH,
+        FILE_CHOOSER_INTERFACE,
+    )
+    .and_then(|chooser| chooser.call(method, &(parent_window, title, &*options)))
+    .map_err(dialog_error)?;
+    if handle.as_str() != request_path {
+        // Old portals ignore the handle token, so we can only subscribe now
+        responses = Proxy::new(
+            connection,
+            PORTAL_DESTINATION,
+            handle.as_str(),
+            REQUEST_INTERFACE,
+        )
+        .and_then(|request| request.receive_signal("Response"))
+        .map_err(dialog_error)?;
+    }
+
+    let message = responses.next().ok_or_else(|| {
+        Error::Dialog(String::from(
+            "The connection was closed before the portal responded",
+        ))
+    })?;
+    let (response, results): (u32, HashMap<String, OwnedValue>) =
+        message.body().deserialize().map_err(dialog_error)?;
+    match response {
+        0 => Ok(Some(results)),
+        1 => Ok(None),
+        _ => Err(Error::Dialog(String::from(
+            "The portal ended the interaction",
+        ))),
+    }
+}
+
+fn dialog_error(error: zbus::Error) -> Error {
+    Error::Dialog(error.to_string())
+}
+
+/// The portal identifies parent windows by strings such as "x11:1c00003".
+/// Wayland surfaces would need to be exported with xdg-foreign on the application's own connection first,
+/// which we cannot reach from the surface, so they are rejected instead of silently showing a non-modal dialog.
+fn parent_window_identifier(parent_window: Option<ParentWindow>) -> Result<String, Error> {
+    match parent_window {
+        Some(ParentWindow::X11(window)) => Ok(format!("x11:{:x}", window)),
+        Some(ParentWindow::Wayland(_)) => Err(Error::InvalidParentWindow),
+        _ => Ok(String::new()),
+    }
+}
+
+/// A filter in the portal's `(sa(us))` form.
+type PortalFilter = (String, Vec<(u32, String)>);
+
+fn portal_filter(filter: &Filter) -> PortalFilter {
+    // Filters created from MIME types are passed through as MIME types (type 1), the others as globs (type 0)
+    let patterns = if filter.mime_types().is_empty() {
+        filter
+            .extensions()
+            .iter()
+            .map(|extension| (0, format!("*.{}", extension)))
+            .collect()
+    } else {
+        filter
+            .mime_types()
+            .iter()
+            .map(|mime_type| (1, mime_type.clone()))
+            .collect()
+    };
+    (filter.name().to_string(), patterns)
+}
+
+/// Adds the filters, and the default filter as the current filter
+/// (the first filter if the default filter is out of range, as in `filters_default_first()`).
+fn add_filters(portal_options: &mut HashMap<&str, Value<'_>>, options: &DialogOptions<'_>) {
+    let current_filter = match options.filters_default_first().first() {
+        Some((_, filter)) => portal_filter(filter),
+        None => return,
+    };
+    let filters: Vec<PortalFilter> = options.filters.iter().map(portal_filter).collect();
+    portal_options.insert("current_filter", Value::from(current_filter));
+    portal_options.insert("filters", Value::from(filters));
+}
+
+/// Finds the filter that the portal returned as `current_filter`.
+/// Portals that do not return it are handled by guessing the filter from the extensions.
+fn selected_filter_index(
+    options: &DialogOptions<'_>,
+    current_filter: Option<OwnedValue>,
+    paths: &[PathBuf],
+) -> Option<usize> {
+    match current_filter.and_then(|value| PortalFilter::try_from(Value::from(value)).ok()) {
+        Some(current_filter) => options
+            .filters
+            .iter()
+            .position(|filter| portal_filter(filter) == current_filter),
+        None => options.guess_filter_index(paths),
+    }
+}
+
+/// The portal wants the folder as a nul-terminated byte array.
+fn add_current_folder(portal_options: &mut HashMap<&str, Value<'_>>, default_path: Option<&Path>) {
+    if let Some(default_path) = default_path {
+        let mut bytes = default_path.as_os_str().as_bytes().to_vec();
+        bytes.push(0);
+        portal_options.insert("current_folder", Value::from(bytes));
+    }
+}
+
+/// Gets the selected paths from the `uris` result of a request.
+fn selected_paths(mut results: HashMap<String, OwnedValue>) -> Result<Vec<PathBuf>, Error> {
+    let uris = results
+        .remove("uris")
+        .ok_or_else(|| Error::Dialog(String::from("The portal did not return any URIs")))
+        .and_then(|uris| Vec::<String>::try_from(uris).map_err(|e| Error::Dialog(e.to_string())))?;
+    uris.iter().map(|uri| uri_to_path(uri)).collect()
+}
+
+/// Converts a `file://` URI of a local file to a path, decoding percent-escapes.
+fn uri_to_path(uri: &str) -> Result<PathBuf, Error> {
+    let unsupported = || Error::Dialog(format!("Unsupported URI returned by the portal: {}", uri));
+    let rest = uri.strip_prefix("file://").ok_or_else(unsupported)?;
+    let (host, encoded) = rest.split_at(rest.find('/').ok_or_else(unsupported)?);
+    // Files on other hosts have no local path
+    if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+        return Err(unsupported());
+    }
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut iter = encoded.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [
+                iter.next().ok_or_else(unsupported)?,
+                iter.next().ok_or_else(unsupported)?,
+            ];
+            let hex = std::str::from_utf8(&hex).map_err(|_| unsupported())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| unsupported())?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    Ok(OsString::from_vec(bytes).into())
+}
@@ -0,0 +1,305 @@
+//! A fallback backend that shows dialogs by running `zenity` or `kdialog`,
+//! for systems where neither GTK nor a desktop portal is available.
+
+use super::Backend;
+use super::DialogKind;
+use super::DialogOptions;
+use super::ParentWindow;
+use crate::path;
+use crate::DialogResult;
+use crate::Error;
+use crate::Filter;
+use crate::MultipleFileResult;
+use crate::MultipleFileSelection;
+use crate::NfdPathSetBuf;
+use crate::Selection;
+use crate::SingleFileResult;
+use crate::SingleFileSelection;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The program that a `SubprocessBackend` runs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Tool {
+    Zenity,
+    KDialog,
+}
+
+impl Tool {
+    /// The name of the executable, which is looked up on `PATH`.
+    pub fn program(self) -> &'static str {
+        match self {
+            Tool::Zenity => "zenity",
+            Tool::KDialog => "kdialog",
+        }
+    }
+}
+
+/// The backend that shows dialogs by running `zenity` or `kdialog` and reading the selection from its output.
+#[derive(Clone, Debug)]
+pub struct SubprocessBackend {
+    tool: Tool,
+}
+
+impl SubprocessBackend {
+    pub fn new(tool: Tool) -> Self {
+        Self { tool }
+    }
+
+    /// Finds the first of `zenity` and `kdialog` that is on `PATH`.
+    pub fn detect() -> Option<Self> {
+        [Tool::Zenity, Tool::KDialog]
+            .iter()
+            .copied()
+            .find(|tool| find_program(tool.program()).is_some())
+            .map(Self::new)
+    }
+
+    pub fn tool(&self) -> Tool {
+        self.tool
+    }
+
+    /// Runs the tool and returns the paths that it printed, one per line.
+    fn run(&self, kind: DialogKind, options: &DialogOptions<'_>) -> DialogResult<Vec<PathBuf>> {
+        let mut command = Command::new(self.tool.program());
+        match self.tool {
+            Tool::Zenity => zenity_args(&mut command, kind, options),
+            Tool::KDialog => kdialog_args(&mut command, kind, options),
+        }
+        let output = match command.output() {
+            Ok(output) => output,
+            Err(error) => {
+                return DialogResult::Err(Error::Dialog(format!(
+                    "Failed to run {}: {}",
+                    self.tool.program(),
+                    error
+                )))
+            }
+        };
+        match output.status.code() {
+            Some(0) => {}
+            Some(1) => return DialogResult::Cancel,
+            _ => {
+                return DialogResult::Err(Error::Dialog(format!(
+                    "{} failed ({}): {}",
+                    self.tool.program(),
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )))
+            }
+        }
+        let paths: Vec<PathBuf> = output
+            .stdout
+            .split(|&byte| byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| OsString::from_vec(line.to_vec()).into())
+            .collect();
+        if paths.is_empty() {
+            return DialogResult::Err(Error::Dialog(format!(
+                "{} did not print a path",
+                self.tool.program()
+            )));
+        }
+        DialogResult::Ok(paths)
+    }
+
+    // Neither tool reports the selected filter, so it is guessed from the extensions
+    fn run_single(&self, kind: DialogKind, options: &DialogOptions<'_>) -> SingleFileSelection {
+        match self.run(kind, options) {
+            DialogResult::Ok(paths) => {
+                let filter_index = options.guess_filter_index(&paths);
+                let mut paths = paths.into_iter();
+                match (paths.next(), paths.next()) {
+                    (Some(path), None) => SingleFileSelection::Ok(Selection {
+                        value: path::owned_path(path),
+                        filter_index,
+                    }),
+                    _ => SingleFileSelection::Err(Error::Dialog(format!(
+                        "{} printed more than one path",
+                        self.tool.program()
+                    ))),
+                }
+            }
+            DialogResult::Cancel => SingleFileSelection::Cancel,
+            DialogResult::Err(error) => SingleFileSelection::Err(error),
+        }
+    }
+
+    fn run_multiple(&self, kind: DialogKind, options: &DialogOptions<'_>) -> MultipleFileSelection {
+        self.run(kind, options).map(|paths| Selection {
+            filter_index: options.guess_filter_index(&paths),
+            value: NfdPathSetBuf::from_paths(paths),
+        })
+    }
+}
+
+impl Backend for SubprocessBackend {
+    fn init(&self) -> Result<(), Error> {
+        if find_program(self.tool.program()).is_none() {
+            return Err(Error::Init(format!(
+                "{} was not found on PATH",
+                self.tool.program()
+            )));
+        }
+        // Both tools are graphical, so they fail without a display (e.g. over SSH without X forwarding)
+        if std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            return Err(Error::Init(String::from("No display is available")));
+        }
+        Ok(())
+    }
+
+    fn quit(&self) {}
+
+    fn open_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        self.run_single(DialogKind::OpenFile, options)
+    }
+
+    fn open_file_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileSelection {
+        self.run_multiple(DialogKind::OpenFileMultiple, options)
+    }
+
+    fn save_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        self.run_single(DialogKind::SaveFile, options)
+    }
+
+    fn pick_folder(&self, options: &DialogOptions<'_>) -> SingleFileResult {
+        self.run_single(DialogKind::PickFolder, options)
+            .map(|selection| selection.value)
+    }
+
+    // kdialog cannot select multiple folders, so it shows a single-folder dialog instead
+    fn pick_folder_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileResult {
+        self.run_multiple(DialogKind::PickFolderMultiple, options)
+            .map(|selection| selection.value)
+    }
+}
+
+fn find_program(program: &str) -> Option<PathBuf> {
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|path| path.is_file())
+    })
+}
+
+/// The path that the dialog starts at: the default name inside the default path for save dialogs,
+/// otherwise the default path itself.
+fn start_path(options: &DialogOptions<'_>) -> Option<PathBuf> {
+    match (options.default_path, options.default_name) {
+        (Some(default_path), Some(default_name)) => Some(default_path.join(default_name)),
+        (None, Some(default_name)) => Some(default_name.to_path_buf()),
+        (default_path, None) => default_path.map(Path::to_path_buf),
+    }
+}
+
+/// Concatenates OS strings, since `--flag=value` arguments may contain non-UTF-8 paths.
+fn concat<A: AsRef<OsStr>, B: AsRef<OsStr>>(a: A, b: B) -> OsString {
+    let mut result = a.as_ref().to_os_string();
+    result.push(b);
+    result
+}
+
+fn zenity_args(command: &mut Command, kind: DialogKind, options: &DialogOptions<'_>) {
+    command
+        .arg("--file-selection")
+        .arg(concat("--title=", kind.title()));
+    match kind {
+        DialogKind::OpenFile => {}
+        DialogKind::OpenFileMultiple => {
+            command.arg("--multiple");
+        }
+        DialogKind::SaveFile => {
+            command.arg("--save");
+            // Older zenity only asks before replacing a file when told to (newer ones always ask, and ignore this)
+            if options.confirm_overwrite {
+                command.arg("--confirm-overwrite");
+            }
+        }
+        DialogKind::PickFolder => {
+            command.arg("--directory");
+        }
+        DialogKind::PickFolderMultiple => {
+            command.arg("--directory").arg("--multiple");
+        }
+    }
+    command.arg("--separator=\n");
+    if let Some(start_path) = start_path(options) {
+        // A trailing slash makes zenity open the folder instead of selecting it
+        let start_path = if options.default_name.is_none() {
+            concat(start_path, "/")
+        } else {
+            start_path.into_os_string()
+        };
+        command.arg(concat("--filename=", start_path));
+    }
+    // e.g. "--file-filter=Source code | *.c *.cpp".  zenity selects the first filter.
+    for (_, filter) in options.filters_default_first() {
+        command.arg(format!(
+            "--file-filter={} | {}",
+            filter.name(),
+            patterns(filter)
+        ));
+    }
+    // zenity has no portable way to attach to a parent window
+}
+
+fn kdialog_args(command: &mut Command, kind: DialogKind, options: &DialogOptions<'_>) {
+    command.arg("--title").arg(kind.title());
+    if let Some(ParentWindow::X11(window)) = options.parent_window {
+        command.arg("--attach").arg(window.to_string());
+    }
+    match kind {
+        DialogKind::OpenFile => {
+            command.arg("--getopenfilename");
+        }
+        DialogKind::OpenFileMultiple => {
+            command
+                .arg("--getopenfilename")
+                .arg("--multiple")
+                .arg("--separate-output");
+        }
+        DialogKind::SaveFile => {
+            command.arg("--getsavefilename");
+        }
+        DialogKind::PickFolder | DialogKind::PickFolderMultiple => {
+            command.arg("--getexistingdirectory");
+        }
+    }
+    // The start path is positional, so it is needed whenever there is a filter after it
+    let start_path = start_path(options);
+    let has_filters = !options.filters.is_empty()
+        && kind != DialogKind::PickFolder
+        && kind != DialogKind::PickFolderMultiple;
+    match start_path {
+        Some(start_path) => {
+            command.arg(start_path);
+        }
+        None if has_filters => {
+            command.arg(".");
+        }
+        None => {}
+    }
+    // e.g. "*.c *.cpp|Source code\n*.h|Headers"
+    if has_filters {
+        let filters: Vec<String> = options
+            .filters_default_first()
+            .into_iter()
+            .map(|(_, filter)| format!("{}|{}", patterns(filter), filter.name()))
+            .collect();
+        command.arg(filters.join("\n"));
+    }
+}
+
+/// Converts the extensions of a filter into glob patterns like "*.c *.cpp".
+fn patterns(filter: &Filter) -> String {
+    filter
+        .extensions()
+        .iter()
+        .map(|extension| format!("*.{}", extension))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,525 @@
+//! A backend that shows an interactive file browser in the terminal, for sessions without a display
+//! (e.g. over SSH).  The browser is drawn on stderr, so stdout can still be redirected.
+//!
+//! Keys: arrows/PageUp/PageDown/Home/End to move, Enter to choose (or open a folder), Right/Left to open a
+//! folder or go to its parent, Space to mark entries in multiple-selection dialogs, Tab to change the filter,
+//! and Esc to cancel.  In save dialogs, typing edits the file name.
+
+use super::Backend;
+use super::DialogKind;
+use super::DialogOptions;
+use crate::path;
+use crate::DialogResult;
+use crate::Error;
+use crate::Filter;
+use crate::MultipleFileResult;
+use crate::MultipleFileSelection;
+use crate::NfdPathSetBuf;
+use crate::Selection;
+use crate::SingleFileResult;
+use crate::SingleFileSelection;
+use crossterm::cursor;
+use crossterm::event;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
+use crossterm::event::KeyEventKind;
+use crossterm::event::KeyModifiers;
+use crossterm::style;
+use crossterm::terminal;
+use crossterm::QueueableCommand;
+use std::ffi::OsString;
+use std::io;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The backend that shows dialogs as a file browser in the terminal.
+#[derive(Clone, Debug, Default)]
+pub struct TuiBackend;
+
+impl TuiBackend {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn run(
+        &self,
+        kind: DialogKind,
+        options: &DialogOptions<'_>,
+    ) -> DialogResult<Selection<Vec<PathBuf>>> {
+        let mut browser = Browser::new(kind, options);
+        let result = match browser.run() {
+            Ok(result) => result,
+            Err(error) => DialogResult::Err(Error::Dialog(error.to_string())),
+        };
+        // `filter_index` is `filters.len()` when all files are shown
+        let filter_index =
+            Some(browser.filter_index).filter(|&index| index < browser.filters.len());
+        result.map(|paths| Selection {
+            value: paths,
+            filter_index,
+        })
+    }
+
+    fn run_single(&self, kind: DialogKind, options: &DialogOptions<'_>) -> SingleFileSelection {
+        match self.run(kind, options) {
+            DialogResult::Ok(selection) => match selection.value.into_iter().next() {
+                Some(path) => SingleFileSelection::Ok(Selection {
+                    value: path::owned_path(path),
+                    filter_index: selection.filter_index,
+                }),
+                None => SingleFileSelection::Cancel,
+            },
+            DialogResult::Cancel => SingleFileSelection::Cancel,
+            DialogResult::Err(error) => SingleFileSelection::Err(error),
+        }
+    }
+
+    fn run_multiple(&self, kind: DialogKind, options: &DialogOptions<'_>) -> MultipleFileSelection {
+        self.run(kind, options).map(|selection| Selection {
+            value: NfdPathSetBuf::from_paths(selection.value),
+            filter_index: selection.filter_index,
+        })
+    }
+}
+
+impl Backend for TuiBackend {
+    fn init(&self) -> Result<(), Error> {
+        if io::stderr().is_terminal() {
+            Ok(())
+        } else {
+            Err(Error::Init(String::from("Not running in a terminal")))
+        }
+    }
+
+    fn quit(&self) {}
+
+    fn open_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        self.run_single(DialogKind::OpenFile, options)
+    }
+
+    fn open_file_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileSelection {
+        self.run_multiple(DialogKind::OpenFileMultiple, options)
+    }
+
+    fn save_file(&self, options: &DialogOptions<'_>) -> SingleFileSelection {
+        self.run_single(DialogKind::SaveFile, options)
+    }
+
+    fn pick_folder(&self, options: &DialogOptions<'_>) -> SingleFileResult {
+        self.run_single(DialogKind::PickFolder, options)
+            .map(|selection| selection.value)
+    }
+
+    fn pick_folder_multiple(&self, options: &DialogOptions<'_>) -> MultipleFileResult {
+        self.run_multiple(DialogKind::PickFolderMultiple, options)
+            .map(|selection| selection.value)
+    }
+}
+
+/// Switches the terminal to raw mode and the alternate screen, and restores it when dropped.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = TerminalGuard;
+        let mut out = io::stderr();
+        out.queue(terminal::EnterAlternateScreen)?
+            .queue(cursor::Hide)?
+            .flush()?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut out = io::stderr();
+        let _ = out
+            .queue(cursor::Show)
+            .and_then(|out| out.queue(terminal::LeaveAlternateScreen))
+            .and_then(|out| out.flush());
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum EntryKind {
+    /// The folder being shown, so that it can be chosen in folder dialogs.
+    Current,
+    Parent,
+    Folder,
+    File,
+}
+
+struct Entry {
+    name: OsString,
+    kind: EntryKind,
+}
+
+struct Browser<'a> {
+    kind: DialogKind,
+    filters: &'a [Filter],
+    /// Index into `filters`, or `filters.len()` for all files.
+    filter_index: usize,
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    cursor: usize,
+    scroll: usize,
+    /// The marked entries of multiple-selection dialogs, in the order they were marked.
+    marked: Vec<PathBuf>,
+    /// The file name being typed in save dialogs.
+    name: String,
+    confirm_overwrite: bool,
+    /// The existing file that the user was asked to confirm replacing, by pressing Enter again.
+    confirming: Option<PathBuf>,
+    message: Option<String>,
+}
+
+impl<'a> Browser<'a> {
+    fn new(kind: DialogKind, options: &DialogOptions<'a>) -> Self {
+        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        let dir = match options.default_path {
+            Some(default_path) if default_path.is_dir() => current_dir.join(default_path),
+            Some(default_path) => match default_path.parent() {
+                Some(parent) if parent.is_dir() => current_dir.join(parent),
+                _ => current_dir,
+            },
+            None => current_dir,
+        };
+        let mut browser = Browser {
+            kind,
+            filters: if is_folder_dialog(kind) {
+                &[]
+            } else {
+                options.filters
+            },
+            filter_index: options.default_filter,
+            dir: dir.canonicalize().unwrap_or(dir),
+            entries: Vec::new(),
+            cursor: 0,
+            scroll: 0,
+            marked: Vec::new(),
+            name: options
+                .default_name
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            confirm_overwrite: options.confirm_overwrite,
+            confirming: None,
+            message: None,
+        };
+        browser.load();
+        browser
+    }
+
+    fn run(&mut self) -> io::Result<DialogResult<Vec<PathBuf>>> {
+        let _guard = TerminalGuard::enter()?;
+        let mut out = io::stderr();
+        loop {
+            self.draw(&mut out)?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Release {
+                    if let Some(result) = self.handle_key(key) {
+                        return Ok(result);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lists the current folder.  Hidden entries are skipped, and files are only listed if they match the filter.
+    fn load(&mut self) {
+        self.entries.clear();
+        self.cursor = 0;
+        self.scroll = 0;
+        if is_folder_dialog(self.kind) {
+            self.entries.push(Entry {
+                name: OsString::from("."),
+                kind: EntryKind::Current,
+            });
+        }
+        if self.dir.parent().is_some() {
+            self.entries.push(Entry {
+                name: OsString::from(".."),
+                kind: EntryKind::Parent,
+            });
+        }
+        let read_dir = match std::fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(error) => {
+                self.message = Some(error.to_string());
+                return;
+            }
+        };
+        let mut entries: Vec<Entry> = read_dir
+            .filter_map(Result::ok)
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|entry| {
+                let kind = if entry.path().is_dir() {
+                    EntryKind::Folder
+                } else if !is_folder_dialog(self.kind) && self.matches_filter(&entry.path()) {
+                    EntryKind::File
+                } else {
+                    return None;
+                };
+                Some(Entry {
+                    name: entry.file_name(),
+                    kind,
+                })
+            })
+            .collect();
+        entries.sort_by_cached_key(|entry| {
+            (
+                entry.kind == EntryKind::File,
+                entry.name.to_string_lossy().to_lowercase(),
+            )
+        });
+        self.entries.extend(entries);
+    }
+
+    fn matches_filter(&self, path: &Path) -> bool {
+        let filter = match self.filters.get(self.filter_index) {
+            Some(filter) => filter,
+            None => return true,
+        };
+        // The extensions of filters are already lowercase
+        let extension = match path.extension() {
+            Some(extension) => extension.to_string_lossy().to_lowercase(),
+            None => return false,
+        };
+        filter.extensions().contains(&extension)
+    }
+
+    fn change_dir(&mut self, dir: PathBuf) {
+        self.message = None;
+        self.dir = dir;
+        self.load();
+    }
+
+    fn go_to_parent(&mut self) {
+        if let Some(parent) = self.dir.parent() {
+            let parent = parent.to_path_buf();
+            self.change_dir(parent);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Option<DialogResult<Vec<PathBuf>>> {
+        let page = (terminal::size().map_or(24, |(_, rows)| rows) as usize).saturating_sub(6);
+        match key.code {
+            KeyCode::Esc => return Some(DialogResult::Cancel),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(DialogResult::Cancel)
+            }
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-(page.max(1) as isize)),
+            KeyCode::PageDown => self.move_cursor(page.max(1) as isize),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.entries.len().saturating_sub(1),
+            KeyCode::Tab if !self.filters.is_empty() => {
+                self.filter_index = (self.filter_index + 1) % (self.filters.len() + 1);
+                self.load();
+            }
+            KeyCode::Left => self.go_to_parent(),
+            KeyCode::Right => self.open_entry(),
+            KeyCode::Enter => return self.activate(),
+            KeyCode::Backspace if self.kind == DialogKind::SaveFile && !self.name.is_empty() => {
+                self.name.pop();
+                self.cancel_confirmation();
+            }
+            KeyCode::Backspace => self.go_to_parent(),
+            KeyCode::Char(c) if self.kind == DialogKind::SaveFile => {
+                self.name.push(c);
+                self.cancel_confirmation();
+            }
+            KeyCode::Char(' ') => self.toggle_mark(),
+            _ => {}
+        }
+        None
+    }
+
+    fn cancel_confirmation(&mut self) {
+        if self.confirming.take().is_some() {
+            self.message = None;
+        }
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1) as isize;
+        self.cursor = (self.cursor as isize + delta).max(0).min(last) as usize;
+    }
+
+    fn entry_path(&self) -> Option<(PathBuf, EntryKind)> {
+        self.entries.get(self.cursor).map(|entry| match entry.kind {
+            EntryKind::Current => (self.dir.clone(), entry.kind),
+            EntryKind::Parent => (
+                self.dir
+                    .parent()
+                    .map_or_else(|| self.dir.clone(), Path::to_path_buf),
+                entry.kind,
+            ),
+            _ => (self.dir.join(&entry.name), entry.kind),
+        })
+    }
+
+    fn open_entry(&mut self) {
+        match self.entry_path() {
+            Some((path, EntryKind::Folder)) | Some((path, EntryKind::Parent)) => {
+                self.change_dir(path)
+            }
+            _ => {}
+        }
+    }
+
+    fn toggle_mark(&mut self) {
+        let markable = match (self.kind, self.entry_path()) {
+            (DialogKind::OpenFileMultiple, Some((path, EntryKind::File))) => Some(path),
+            (DialogKind::PickFolderMultiple, Some((path, EntryKind::Folder))) => Some(path),
+            _ => None,
+        };
+        if let Some(path) = markable {
+            match self.marked.iter().position(|marked| *marked == path) {
+                Some(index) => {
+                    self.marked.remove(index);
+                }
+                None => self.marked.push(path),
+            }
+            self.move_cursor(1);
+        }
+    }
+
+    /// Handles Enter: chooses the entry under the cursor (or the marked entries), or opens a folder.
+    fn activate(&mut self) -> Option<DialogResult<Vec<PathBuf>>> {
+        if self.kind == DialogKind::SaveFile && !self.name.is_empty() {
+            let path = self.dir.join(&self.name);
+            if self.confirm_overwrite && path.exists() && self.confirming.as_ref() != Some(&path) {
+                self.message = Some(format!(
+                    "{} already exists.  Press Enter again to replace it.",
+                    self.name
+                ));
+                self.confirming = Some(path);
+                return None;
+            }
+            return Some(DialogResult::Ok(vec![path]));
+        }
+        match self.entry_path() {
+            Some((path, EntryKind::Current)) | Some((path, EntryKind::File)) => {
+                if self.kind == DialogKind::SaveFile {
+                    // Choosing an existing file fills in its name, so that it can be confirmed with Enter
+                    self.name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    None
+                } else if self.marked.is_empty() {
+                    Some(DialogResult::Ok(vec![path]))
+                } else {
+                    Some(DialogResult::Ok(std::mem::take(&mut self.marked)))
+                }
+            }
+            Some((path, EntryKind::Folder)) | Some((path, EntryKind::Parent)) => {
+                self.change_dir(path);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn draw<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let (columns, rows) = terminal::size()?;
+        let columns = columns as usize;
+        let rows = rows as usize;
+
+        let mut header = vec![format!("{} - {}", self.kind.title(), self.dir.display())];
+        if !self.filters.is_empty() {
+            let filter = match self.filters.get(self.filter_index) {
+                Some(filter) => format!("{} ({})", filter.name(), filter.spec()),
+                None => String::from("All files"),
+            };
+            header.push(format!("Filter: {}  [Tab to change]", filter));
+        }
+        let mut footer = Vec::new();
+        if self.kind == DialogKind::SaveFile {
+            footer.push(format!("Name: {}", self.name));
+        }
+        footer.push(match &self.message {
+            Some(message) => message.clone(),
+            None => String::from(help(self.kind)),
+        });
+        let list_rows = rows.saturating_sub(header.len() + footer.len()).max(1);
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + list_rows {
+            self.scroll = self.cursor + 1 - list_rows;
+        }
+
+        out.queue(terminal::Clear(terminal::ClearType::All))?;
+        let mut row = 0;
+        for line in &header {
+            out.queue(cursor::MoveTo(0, row as u16))?
+                .queue(style::Print(truncate(line, columns)))?;
+            row += 1;
+        }
+        let multiple = self.kind == DialogKind::OpenFileMultiple
+            || self.kind == DialogKind::PickFolderMultiple;
+        for (index, entry) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(list_rows)
+        {
+            let mark = if !multiple {
+                ""
+            } else if self.marked.contains(&self.dir.join(&entry.name)) {
+                "[x] "
+            } else {
+                "[ ] "
+            };
+            let suffix = match entry.kind {
+                EntryKind::File => "",
+                _ => "/",
+            };
+            let line = format!("{}{}{}", mark, entry.name.to_string_lossy(), suffix);
+            out.queue(cursor::MoveTo(0, row as u16))?;
+            if index == self.cursor {
+                out.queue(style::SetAttribute(style::Attribute::Reverse))?
+                    .queue(style::Print(truncate(&line, columns)))?
+                    .queue(style::SetAttribute(style::Attribute::Reset))?;
+            } else {
+                out.queue(style::Print(truncate(&line, columns)))?;
+            }
+            row += 1;
+        }
+        for (index, line) in footer.iter().enumerate() {
+            let row = rows.saturating_sub(footer.len() - index).max(row);
+            out.queue(cursor::MoveTo(0, row as u16))?
+                .queue(style::Print(truncate(line, columns)))?;
+        }
+        out.flush()
+    }
+}
+
+fn is_folder_dialog(kind: DialogKind) -> bool {
+    kind == DialogKind::PickFolder || kind == DialogKind::PickFolderMultiple
+}
+
+fn help(kind: DialogKind) -> &'static str {
+    match kind {
+        DialogKind::OpenFile => "Enter: open  Left: parent folder  Esc: cancel",
+        DialogKind::OpenFileMultiple => {
+            "Space: mark  Enter: open marked (or current)  Left: parent folder  Esc: cancel"
+        }
+        DialogKind::SaveFile => "Type a name, Enter: save  Left: parent folder  Esc: cancel",
+        DialogKind::PickFolder => "Enter on '.': select  Enter: open folder  Esc: cancel",
+        DialogKind::PickFolderMultiple => {
+            "Space: mark  Enter on '.': select marked (or current)  Esc: cancel"
+        }
+    }
+}
+
+fn truncate(line: &str, columns: usize) -> String {
+    line.chars().take(columns).collect()
+}
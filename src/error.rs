@@ -0,0 +1,144 @@
+use crate::backend::BackendKind;
+use std::fmt;
+use std::path::PathBuf;
+
+/// The error type returned by all fallible operations in this crate.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Error {
+    /// A filter (e.g. one passed to `Filter::new` or `add_filter`) is invalid.  The payload says why.
+    InvalidFilter(FilterError),
+    /// A path or string has an interior nul character, so it cannot be passed to NFDe.
+    InteriorNul,
+    /// The parent window handle is unavailable, or is of a kind that is not supported.
+    /// Also returned when showing a dialog with a Wayland parent window with NFDe or the XDG portal backend,
+    /// which cannot make the dialog modal to it.
+    InvalidParentWindow,
+    /// A backend could not be initialized (e.g. there is no display to connect to).
+    Init(String),
+    /// None of the backends tried by `Nfd::new()` or `NfdBuilder::init()` could be initialized.
+    /// The payload has the error of each backend, in the order they were tried.
+    NoBackend(Vec<(BackendKind, Error)>),
+    /// A dialog was shown on a thread that does not hold an `Nfd`.
+    NotInitialized,
+    /// The backend cannot be used on this thread.  On macOS, NFDe can only be used by one thread at a time
+    /// (AppKit is not thread-safe), so this is returned when another thread holds an `Nfd` with NFDe,
+    /// and by `show_async()` with NFDe.
+    WrongThread,
+    /// The selected paths do not match any of the filters of the dialog.
+    /// Only returned by builders with `validate_selection(true)`.  The payload has every path that did not match.
+    FilterMismatch(Vec<PathBuf>),
+    /// The file chosen in a save dialog already exists, and the builder has `OverwritePolicy::Forbid`.
+    FileExists(PathBuf),
+    /// The folder of the file chosen in a save dialog does not exist, and the builder has `MissingParentPolicy::Forbid`.
+    MissingParent(PathBuf),
+    /// The folder of the file chosen in a save dialog could not be created.  The payload has the folder and the reason.
+    CreateParent(PathBuf, String),
+    /// The native dialog failed.  The payload is the message reported by NFDe.
+    Dialog(String),
+    /// The paths in a `NfdPathSetBuf` could not be enumerated.
+    PathSetEnum(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidFilter(reason) => write!(f, "Invalid filter: {}", reason),
+            Error::InteriorNul => f.write_str("Path has interior null values"),
+            Error::InvalidParentWindow => {
+                f.write_str("Parent window handle is unavailable or unsupported")
+            }
+            Error::Init(msg) => write!(f, "Failed to initialize NFD: {}", msg),
+            Error::NoBackend(errors) => {
+                f.write_str("No backend could be initialized")?;
+                for (index, (kind, error)) in errors.iter().enumerate() {
+                    let separator = if index == 0 { " (" } else { "; " };
+                    write!(f, "{}{}: {}", separator, kind, error)?;
+                }
+                if !errors.is_empty() {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+            Error::NotInitialized => f.write_str("NFD is not initialized on the current thread"),
+            Error::WrongThread => f.write_str("The backend cannot show dialogs on this thread"),
+            Error::FilterMismatch(paths) => {
+                f.write_str("Selected paths do not match any filter")?;
+                for (index, path) in paths.iter().enumerate() {
+                    let separator = if index == 0 { " (" } else { ", " };
+                    write!(f, "{}{}", separator, path.display())?;
+                }
+                if !paths.is_empty() {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+            Error::FileExists(path) => write!(f, "File already exists: {}", path.display()),
+            Error::MissingParent(path) => write!(f, "Folder does not exist: {}", path.display()),
+            Error::CreateParent(path, msg) => {
+                write!(f, "Failed to create folder {}: {}", path.display(), msg)
+            }
+            Error::Dialog(msg) => write!(f, "Dialog failed: {}", msg),
+            Error::PathSetEnum(msg) => write!(f, "Failed to enumerate path set: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Why a filter is invalid, as returned in `Error::InvalidFilter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum FilterError {
+    /// The filter has no extensions.
+    NoExtensions,
+    /// The filter has no MIME types.
+    NoMimeTypes,
+    /// No extensions are known for any of the MIME types of the filter.
+    UnknownMimeTypes,
+    /// The filter specification is empty.
+    EmptySpec,
+    /// The filter specification mixes extensions and MIME types (e.g. "png,image/jpeg").
+    MixedSpec,
+    /// An extension is empty (e.g. in "png,,jpg").
+    EmptyExtension,
+    /// An extension contains a wildcard (e.g. "*.png").
+    ExtensionWildcard,
+    /// An extension contains a dot (e.g. ".png").
+    ExtensionDot,
+    /// An extension contains a separator (e.g. "png;jpg").
+    ExtensionSeparator,
+    /// An extension contains whitespace.
+    ExtensionWhitespace,
+    /// A MIME type is not of the form "type/subtype" or "type/*".
+    MalformedMimeType,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FilterError::NoExtensions => "Filter has no extensions",
+            FilterError::NoMimeTypes => "Filter has no MIME types",
+            FilterError::UnknownMimeTypes => {
+                "No extensions are known for the MIME types of the filter"
+            }
+            FilterError::EmptySpec => "Filter specification is empty",
+            FilterError::MixedSpec => "Filter specification mixes extensions and MIME types",
+            FilterError::EmptyExtension => "Filter extension is empty",
+            FilterError::ExtensionWildcard => "Filter extension contains a wildcard",
+            FilterError::ExtensionDot => "Filter extension contains a dot",
+            FilterError::ExtensionSeparator => "Filter extension contains a separator",
+            FilterError::ExtensionWhitespace => "Filter extension contains whitespace",
+            FilterError::MalformedMimeType => "Filter MIME type is malformed",
+        })
+    }
+}
+
+// Lets `Filter`s be passed wherever filters that may need validation are accepted
+impl From<std::convert::Infallible> for Error {
+    fn from(never: std::convert::Infallible) -> Self {
+        match never {}
+    }
+}
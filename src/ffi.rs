@@ -1,5 +1,7 @@
 #![allow(non_camel_case_types)]
 #![allow(non_upper_case_globals)]
+// Without NFDe, only the types that the native filters are built from are used
+#![cfg_attr(not(nfd_native), allow(dead_code))]
 
 use std::os::raw::c_char;
 use std::os::raw::c_uint;
@@ -15,6 +17,12 @@ pub type nfdfiltersize_t = c_uint;
 
 pub type nfdpathset_t = c_void;
 
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+pub type nfdpathsetsize_t = std::os::raw::c_ulong; // DWORD / NSUInteger
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub type nfdpathsetsize_t = c_uint; // guint
+
 #[repr(C)]
 #[allow(dead_code)]
 pub enum nfdresult_t {
@@ -26,55 +34,115 @@ pub enum nfdresult_t {
 #[repr(C)]
 #[allow(dead_code)]
 pub struct nfdnfilteritem_t {
-    pub name: *mut nfdnchar_t, // note: the C API actually has *const nfdnchar_t, but we use *mut to avoid const casts here
-    pub spec: *mut nfdnchar_t,
+    pub name: *const nfdnchar_t,
+    pub spec: *const nfdnchar_t,
+}
+
+pub type nfdversion_t = usize;
+
+pub const NFD_INTERFACE_VERSION: nfdversion_t = 1;
+
+pub const NFD_WINDOW_HANDLE_TYPE_UNSET: usize = 0;
+#[allow(dead_code)]
+pub const NFD_WINDOW_HANDLE_TYPE_WINDOWS: usize = 1;
+#[allow(dead_code)]
+pub const NFD_WINDOW_HANDLE_TYPE_COCOA: usize = 2;
+#[allow(dead_code)]
+pub const NFD_WINDOW_HANDLE_TYPE_X11: usize = 3;
+
+#[repr(C)]
+pub struct nfdwindowhandle_t {
+    pub type_: usize,
+    pub handle: *mut c_void,
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+pub struct nfdopendialognargs_t {
+    pub filterList: *const nfdnfilteritem_t,
+    pub filterCount: nfdfiltersize_t,
+    pub defaultPath: *const nfdnchar_t,
+    pub parentWindow: nfdwindowhandle_t,
 }
 
-// We are using ptr==NULL to represent the lack of an enum (i.e. we already iterated to the end, or acquiring the enum failed).
-// For Linux, it automatically becomes NULL when we reach the end, but we don't need to free the enum in Linux so it is okay.
+#[repr(C)]
+#[allow(non_snake_case)]
+pub struct nfdsavedialognargs_t {
+    pub filterList: *const nfdnfilteritem_t,
+    pub filterCount: nfdfiltersize_t,
+    pub defaultPath: *const nfdnchar_t,
+    pub defaultName: *const nfdnchar_t,
+    pub parentWindow: nfdwindowhandle_t,
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+pub struct nfdpickfoldernargs_t {
+    pub defaultPath: *const nfdnchar_t,
+    pub parentWindow: nfdwindowhandle_t,
+}
+
+// Enumerates a path set in order.  On Linux, it only points into the path set, so it does not need to be freed.
 #[repr(C)]
 #[allow(dead_code)]
 pub struct nfdpathsetenum_t {
     pub ptr: *mut c_void,
 }
 
+// Miri cannot run NFDe, so it runs against a stand-in instead
+#[cfg(nfd_fake_ffi)]
+mod fake;
+#[cfg(nfd_fake_ffi)]
+pub use fake::*;
+
+#[cfg(all(nfd_native, not(nfd_fake_ffi)))]
 extern "C" {
     pub fn NFD_Init() -> nfdresult_t;
     pub fn NFD_Quit();
     pub fn NFD_GetError() -> *const c_char;
+    pub fn NFD_ClearError();
     pub fn NFD_FreePathN(filePath: *mut nfdnchar_t);
-    pub fn NFD_OpenDialogN(
+    pub fn NFD_OpenDialogN_With_Impl(
+        version: nfdversion_t,
         outPath: *mut *mut nfdnchar_t,
-        filterList: *const nfdnfilteritem_t,
-        filterCount: nfdfiltersize_t,
-        defaultPath: *const nfdnchar_t,
+        args: *const nfdopendialognargs_t,
     ) -> nfdresult_t;
-    pub fn NFD_OpenDialogMultipleN(
+    pub fn NFD_OpenDialogMultipleN_With_Impl(
+        version: nfdversion_t,
         outPaths: *mut *mut nfdpathset_t,
-        filterList: *const nfdnfilteritem_t,
-        filterCount: nfdfiltersize_t,
-        defaultPath: *const nfdnchar_t,
+        args: *const nfdopendialognargs_t,
     ) -> nfdresult_t;
-    pub fn NFD_SaveDialogN(
+    pub fn NFD_SaveDialogN_With_Impl(
+        version: nfdversion_t,
         outPath: *mut *mut nfdnchar_t,
-        filterList: *const nfdnfilteritem_t,
-        filterCount: nfdfiltersize_t,
-        defaultPath: *const nfdnchar_t,
-        defaultName: *const nfdnchar_t,
+        args: *const nfdsavedialognargs_t,
     ) -> nfdresult_t;
-    pub fn NFD_PickFolderN(
+    pub fn NFD_PickFolderN_With_Impl(
+        version: nfdversion_t,
         outPath: *mut *mut nfdnchar_t,
-        defaultPath: *const nfdnchar_t,
+        args: *const nfdpickfoldernargs_t,
+    ) -> nfdresult_t;
+    pub fn NFD_PickFolderMultipleN_With_Impl(
+        version: nfdversion_t,
+        outPaths: *mut *mut nfdpathset_t,
+        args: *const nfdpickfoldernargs_t,
     ) -> nfdresult_t;
     pub fn NFD_PathSet_Free(pathSet: *mut nfdpathset_t);
+    pub fn NFD_PathSet_GetCount(
+        pathSet: *const nfdpathset_t,
+        count: *mut nfdpathsetsize_t,
+    ) -> nfdresult_t;
+    pub fn NFD_PathSet_GetPathN(
+        pathSet: *const nfdpathset_t,
+        index: nfdpathsetsize_t,
+        outPath: *mut *mut nfdnchar_t,
+    ) -> nfdresult_t;
     pub fn NFD_PathSet_GetEnum(
-        pathSet: *mut nfdpathset_t,
+        pathSet: *const nfdpathset_t,
         outEnumerator: *mut nfdpathsetenum_t,
     ) -> nfdresult_t;
-
     #[cfg(not(target_os = "linux"))]
     pub fn NFD_PathSet_FreeEnum(enumerator: *mut nfdpathsetenum_t);
-
     pub fn NFD_PathSet_EnumNextN(
         enumerator: *mut nfdpathsetenum_t,
         outPath: *mut *mut nfdnchar_t,
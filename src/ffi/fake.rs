@@ -0,0 +1,198 @@
+//! A stand-in for NFDe, written in Rust so that Miri can check the code that uses NFDe.
+//! Built instead of NFDe under Miri (see build.rs).
+//!
+//! Path sets behave like those of the GTK implementation: a path set is a linked list of paths,
+//! the paths returned by `NFD_PathSet_GetPathN` and `NFD_PathSet_EnumNextN` point into the list,
+//! `NFD_PathSet_FreePathN` does nothing, and `NFD_PathSet_Free` frees the list with its paths.
+//! Dialogs are cancelled, since there is no user to answer them,
+//! and fail if they are not shown on the thread that initialized NFDe, like GTK requires.
+
+#![allow(non_snake_case)]
+
+use super::nfdnchar_t;
+use super::nfdopendialognargs_t;
+use super::nfdpathset_t;
+use super::nfdpathsetenum_t;
+use super::nfdpathsetsize_t;
+use super::nfdpickfoldernargs_t;
+use super::nfdresult_t;
+use super::nfdsavedialognargs_t;
+use super::nfdversion_t;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::os::raw::c_void;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::thread::ThreadId;
+
+// A node of the list that a path set points to, like a `GSList` of strings
+struct Node {
+    path: *mut nfdnchar_t,
+    next: *mut Node,
+}
+
+// The thread that initialized NFDe
+static INIT_THREAD: Mutex<Option<ThreadId>> = Mutex::new(None);
+static HAS_ERROR: AtomicBool = AtomicBool::new(false);
+
+fn init_thread() -> std::sync::MutexGuard<'static, Option<ThreadId>> {
+    match INIT_THREAD.lock() {
+        Ok(thread) => thread,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Cancels the dialog, or fails if it is shown on the wrong thread.
+fn show() -> nfdresult_t {
+    if *init_thread() == Some(std::thread::current().id()) {
+        nfdresult_t::NFD_CANCEL
+    } else {
+        HAS_ERROR.store(true, Ordering::SeqCst);
+        nfdresult_t::NFD_ERROR
+    }
+}
+
+/// Allocates a path, like NFDe does for the path returned by a dialog.
+#[cfg(test)]
+pub fn new_path(path: &str) -> *mut nfdnchar_t {
+    CString::new(path).unwrap().into_raw()
+}
+
+/// Allocates a path set with these paths, like NFDe does for the paths returned by a dialog.
+#[cfg(test)]
+pub fn new_path_set(paths: &[&str]) -> *mut nfdpathset_t {
+    let mut head: *mut Node = std::ptr::null_mut();
+    for path in paths.iter().rev() {
+        head = Box::into_raw(Box::new(Node {
+            path: new_path(path),
+            next: head,
+        }));
+    }
+    head as *mut nfdpathset_t
+}
+
+pub unsafe fn NFD_Init() -> nfdresult_t {
+    *init_thread() = Some(std::thread::current().id());
+    nfdresult_t::NFD_OKAY
+}
+
+pub unsafe fn NFD_Quit() {
+    *init_thread() = None;
+}
+
+pub unsafe fn NFD_GetError() -> *const c_char {
+    if HAS_ERROR.load(Ordering::SeqCst) {
+        b"Dialog shown on a thread that did not initialize NFDe\0".as_ptr() as *const c_char
+    } else {
+        std::ptr::null()
+    }
+}
+
+pub unsafe fn NFD_ClearError() {
+    HAS_ERROR.store(false, Ordering::SeqCst);
+}
+
+pub unsafe fn NFD_FreePathN(filePath: *mut nfdnchar_t) {
+    drop(CString::from_raw(filePath));
+}
+
+pub unsafe fn NFD_OpenDialogN_With_Impl(
+    _version: nfdversion_t,
+    _outPath: *mut *mut nfdnchar_t,
+    _args: *const nfdopendialognargs_t,
+) -> nfdresult_t {
+    show()
+}
+
+pub unsafe fn NFD_OpenDialogMultipleN_With_Impl(
+    _version: nfdversion_t,
+    _outPaths: *mut *mut nfdpathset_t,
+    _args: *const nfdopendialognargs_t,
+) -> nfdresult_t {
+    show()
+}
+
+pub unsafe fn NFD_SaveDialogN_With_Impl(
+    _version: nfdversion_t,
+    _outPath: *mut *mut nfdnchar_t,
+    _args: *const nfdsavedialognargs_t,
+) -> nfdresult_t {
+    show()
+}
+
+pub unsafe fn NFD_PickFolderN_With_Impl(
+    _version: nfdversion_t,
+    _outPath: *mut *mut nfdnchar_t,
+    _args: *const nfdpickfoldernargs_t,
+) -> nfdresult_t {
+    show()
+}
+
+pub unsafe fn NFD_PickFolderMultipleN_With_Impl(
+    _version: nfdversion_t,
+    _outPaths: *mut *mut nfdpathset_t,
+    _args: *const nfdpickfoldernargs_t,
+) -> nfdresult_t {
+    show()
+}
+
+pub unsafe fn NFD_PathSet_Free(pathSet: *mut nfdpathset_t) {
+    let mut node = pathSet as *mut Node;
+    while !node.is_null() {
+        let boxed = Box::from_raw(node);
+        drop(CString::from_raw(boxed.path));
+        node = boxed.next;
+    }
+}
+
+pub unsafe fn NFD_PathSet_GetCount(
+    pathSet: *const nfdpathset_t,
+    count: *mut nfdpathsetsize_t,
+) -> nfdresult_t {
+    let mut len = 0;
+    let mut node = pathSet as *const Node;
+    while !node.is_null() {
+        len += 1;
+        node = (*node).next;
+    }
+    *count = len;
+    nfdresult_t::NFD_OKAY
+}
+
+pub unsafe fn NFD_PathSet_GetPathN(
+    pathSet: *const nfdpathset_t,
+    index: nfdpathsetsize_t,
+    outPath: *mut *mut nfdnchar_t,
+) -> nfdresult_t {
+    let mut node = pathSet as *const Node;
+    for _ in 0..index {
+        node = (*node).next;
+    }
+    *outPath = (*node).path;
+    nfdresult_t::NFD_OKAY
+}
+
+pub unsafe fn NFD_PathSet_GetEnum(
+    pathSet: *const nfdpathset_t,
+    outEnumerator: *mut nfdpathsetenum_t,
+) -> nfdresult_t {
+    (*outEnumerator).ptr = pathSet as *mut c_void;
+    nfdresult_t::NFD_OKAY
+}
+
+pub unsafe fn NFD_PathSet_EnumNextN(
+    enumerator: *mut nfdpathsetenum_t,
+    outPath: *mut *mut nfdnchar_t,
+) -> nfdresult_t {
+    let node = (*enumerator).ptr as *const Node;
+    if node.is_null() {
+        *outPath = std::ptr::null_mut();
+    } else {
+        *outPath = (*node).path;
+        (*enumerator).ptr = (*node).next as *mut c_void;
+    }
+    nfdresult_t::NFD_OKAY
+}
+
+pub unsafe fn NFD_PathSet_FreePathN(_filePath: *mut nfdnchar_t) {}
@@ -0,0 +1,410 @@
+use crate::mime;
+use crate::Error;
+use crate::FilterError;
+use std::convert::TryFrom;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// A named list of file extensions that a dialog can show, e.g. "Images" with "png" and "jpg".
+///
+/// Extensions are given without the leading dot, and are normalized to lowercase.
+/// Filters can also be created from MIME types with `from_mime_types()`.
+/// `(name, spec)` string pairs such as `("Images", "png,jpg")` can be converted into a `Filter` with `TryFrom`,
+/// so they can still be passed to `add_filter()` and `add_filters()`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "FilterData")
+)]
+pub struct Filter {
+    name: String,
+    extensions: Vec<String>,
+    mime_types: Vec<String>,
+}
+
+impl Filter {
+    /// Creates a filter from a list of extensions, e.g. `Filter::new("Images", &["png", "jpg"])`.
+    ///
+    /// Fails with `Error::InvalidFilter` if there are no extensions, or if an extension is empty or contains
+    /// a wildcard, a dot, a separator or whitespace (e.g. "*.png", ".png" or "png;jpg").
+    pub fn new<N: Into<String>, I: IntoIterator>(name: N, extensions: I) -> Result<Self, Error>
+    where
+        I::Item: AsRef<str>,
+    {
+        let name = name.into();
+        if name.contains('\0') {
+            return Err(Error::InteriorNul);
+        }
+        let extensions = extensions
+            .into_iter()
+            .map(|extension| validate_extension(extension.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if extensions.is_empty() {
+            return Err(Error::InvalidFilter(FilterError::NoExtensions));
+        }
+        Ok(Filter {
+            name,
+            extensions,
+            mime_types: Vec::new(),
+        })
+    }
+
+    /// Creates a filter from MIME types such as "image/png", or wildcards such as "image/*".
+    ///
+    /// The MIME types are expanded into extensions with the shared-mime-info database on Linux,
+    /// and with a bundled table of common types elsewhere, so the filter works with every backend.
+    /// Backends that support MIME types (the `xdg-portal` backend) are given the MIME types themselves.
+    ///
+    /// Fails with `Error::InvalidFilter` if a MIME type is malformed, or if no extensions are known for any of them.
+    pub fn from_mime_types<N: Into<String>, I: IntoIterator>(
+        name: N,
+        mime_types: I,
+    ) -> Result<Self, Error>
+    where
+        I::Item: AsRef<str>,
+    {
+        let name = name.into();
+        if name.contains('\0') {
+            return Err(Error::InteriorNul);
+        }
+        let mime_types = mime_types
+            .into_iter()
+            .map(|mime_type| validate_mime_type(mime_type.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if mime_types.is_empty() {
+            return Err(Error::InvalidFilter(FilterError::NoMimeTypes));
+        }
+        let mut extensions: Vec<String> = Vec::new();
+        for extension in mime_types
+            .iter()
+            .flat_map(|mime_type| mime::extensions(mime_type))
+        {
+            if !extensions.contains(&extension) {
+                extensions.push(extension);
+            }
+        }
+        if extensions.is_empty() {
+            return Err(Error::InvalidFilter(FilterError::UnknownMimeTypes));
+        }
+        Ok(Filter {
+            name,
+            extensions,
+            mime_types,
+        })
+    }
+
+    /// Creates a filter from a comma-separated list of extensions, e.g. `Filter::from_spec("Images", "png,jpg")`,
+    /// or of MIME types, e.g. `Filter::from_spec("Images", "image/png,image/jpeg")`.
+    /// Fails with `Error::InvalidFilter` if it has both extensions and MIME types.
+    pub fn from_spec<N: Into<String>>(name: N, spec: &str) -> Result<Self, Error> {
+        if spec.is_empty() {
+            return Err(Error::InvalidFilter(FilterError::EmptySpec));
+        }
+        // Extensions cannot contain slashes, so this is unambiguous
+        if !spec.contains('/') {
+            Self::new(name, spec.split(','))
+        } else if spec
+            .split(',')
+            .any(|part| !part.is_empty() && !part.contains('/'))
+        {
+            Err(Error::InvalidFilter(FilterError::MixedSpec))
+        } else {
+            Self::from_mime_types(name, spec.split(','))
+        }
+    }
+
+    /// Creates a filter from parts that are already validated and lowercase.
+    pub(crate) fn from_parts(
+        name: String,
+        extensions: Vec<String>,
+        mime_types: Vec<String>,
+    ) -> Self {
+        Filter {
+            name,
+            extensions,
+            mime_types,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The extensions, in lowercase and without the leading dot.
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// The MIME types that the filter was created from, in lowercase.  Empty if it was created from extensions.
+    pub fn mime_types(&self) -> &[String] {
+        &self.mime_types
+    }
+
+    /// Whether the extension of the file name of `path` is one of the extensions of this filter, ignoring case.
+    pub fn matches<P: AsRef<Path>>(&self, path: P) -> bool {
+        path.as_ref()
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|extension| self.extensions.contains(&extension.to_lowercase()))
+    }
+
+    /// The comma-separated list of extensions, e.g. "png,jpg".
+    pub fn spec(&self) -> String {
+        self.extensions.join(",")
+    }
+}
+
+fn validate_extension(extension: &str) -> Result<String, Error> {
+    if extension.is_empty() {
+        Err(Error::InvalidFilter(FilterError::EmptyExtension))
+    } else if extension.contains('\0') {
+        Err(Error::InteriorNul)
+    } else if extension.contains(['*', '?']) {
+        Err(Error::InvalidFilter(FilterError::ExtensionWildcard))
+    } else if extension.contains('.') {
+        Err(Error::InvalidFilter(FilterError::ExtensionDot))
+    } else if extension.contains([',', ';', '|', '/', '\\']) {
+        Err(Error::InvalidFilter(FilterError::ExtensionSeparator))
+    } else if extension.contains(char::is_whitespace) {
+        Err(Error::InvalidFilter(FilterError::ExtensionWhitespace))
+    } else {
+        Ok(extension.to_lowercase())
+    }
+}
+
+fn validate_mime_type(mime_type: &str) -> Result<String, Error> {
+    let mut parts = mime_type.split('/');
+    let valid = match (parts.next(), parts.next(), parts.next()) {
+        (Some(media_type), Some(subtype), None) => {
+            let valid_part = |part: &str| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+            };
+            valid_part(media_type) && (subtype == "*" || valid_part(subtype))
+        }
+        _ => false,
+    };
+    if valid {
+        Ok(mime_type.to_ascii_lowercase())
+    } else {
+        Err(Error::InvalidFilter(FilterError::MalformedMimeType))
+    }
+}
+
+impl<N: Into<String>, S: AsRef<str>> TryFrom<(N, S)> for Filter {
+    type Error = Error;
+    fn try_from((name, spec): (N, S)) -> Result<Self, Error> {
+        Self::from_spec(name, spec.as_ref())
+    }
+}
+
+/// The fields of a deserialized `Filter`, which are validated like those of a new filter.
+/// The extensions are kept as they are, rather than expanded again from the MIME types,
+/// so the filter is the same as the one that was serialized.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct FilterData {
+    name: String,
+    extensions: Vec<String>,
+    mime_types: Vec<String>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<FilterData> for Filter {
+    type Error = Error;
+    fn try_from(data: FilterData) -> Result<Self, Error> {
+        let mut filter = Filter::new(data.name, data.extensions)?;
+        filter.mime_types = data
+            .mime_types
+            .iter()
+            .map(|mime_type| validate_mime_type(mime_type))
+            .collect::<Result<_, _>>()?;
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(error: FilterError) -> Result<Filter, Error> {
+        Err(Error::InvalidFilter(error))
+    }
+
+    #[test]
+    fn extensions_are_lowercased() {
+        let filter = Filter::new("Images", &["PNG", "Jpg"]).unwrap();
+        assert_eq!(filter.extensions(), ["png", "jpg"]);
+        assert!(filter.mime_types().is_empty());
+        assert_eq!(filter.spec(), "png,jpg");
+        assert!(filter.matches("a.JPG"));
+        assert!(!filter.matches("a.gif"));
+        assert!(!filter.matches("png"));
+    }
+
+    #[test]
+    fn filters_need_extensions() {
+        assert_eq!(
+            Filter::new("Empty", &[] as &[&str]),
+            invalid(FilterError::NoExtensions)
+        );
+    }
+
+    #[test]
+    fn invalid_extensions_are_rejected() {
+        let cases = [
+            ("", FilterError::EmptyExtension),
+            ("*.png", FilterError::ExtensionWildcard),
+            ("*", FilterError::ExtensionWildcard),
+            ("pn?", FilterError::ExtensionWildcard),
+            (".png", FilterError::ExtensionDot),
+            ("tar.gz", FilterError::ExtensionDot),
+            ("png;jpg", FilterError::ExtensionSeparator),
+            ("png|jpg", FilterError::ExtensionSeparator),
+            ("a/b", FilterError::ExtensionSeparator),
+            ("a\\b", FilterError::ExtensionSeparator),
+            ("png ", FilterError::ExtensionWhitespace),
+            ("p\tng", FilterError::ExtensionWhitespace),
+        ];
+        for (extension, error) in cases.iter() {
+            assert_eq!(
+                Filter::new("Files", &["txt", extension]),
+                invalid(*error),
+                "{:?}",
+                extension
+            );
+        }
+        assert_eq!(Filter::new("Files", &["t\0xt"]), Err(Error::InteriorNul));
+        assert_eq!(Filter::new("Fi\0les", &["txt"]), Err(Error::InteriorNul));
+    }
+
+    #[test]
+    fn specs_are_split_at_commas() {
+        assert_eq!(
+            Filter::from_spec("Images", "PNG,jpg"),
+            Filter::new("Images", &["png", "jpg"])
+        );
+        assert_eq!(
+            Filter::from_spec("Images", ""),
+            invalid(FilterError::EmptySpec)
+        );
+        assert_eq!(
+            Filter::from_spec("Images", "png,,jpg"),
+            invalid(FilterError::EmptyExtension)
+        );
+        assert_eq!(
+            Filter::from_spec("Images", "png,"),
+            invalid(FilterError::EmptyExtension)
+        );
+        assert_eq!(
+            Filter::from_spec("Images", "png, jpg"),
+            invalid(FilterError::ExtensionWhitespace)
+        );
+    }
+
+    #[test]
+    fn string_pairs_convert_into_filters() {
+        assert_eq!(
+            Filter::try_from(("Text", "txt,md")),
+            Filter::new("Text", &["txt", "md"])
+        );
+        assert_eq!(
+            Filter::try_from((String::from("Text"), String::from("TXT"))),
+            Filter::new("Text", &["txt"])
+        );
+        assert_eq!(
+            Filter::try_from(("Text", "*.txt")),
+            invalid(FilterError::ExtensionWildcard)
+        );
+        assert_eq!(
+            Filter::try_from(("Text", "")),
+            invalid(FilterError::EmptySpec)
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "reads the shared-mime-info database")]
+    fn mime_types_are_expanded_into_extensions() {
+        let filter = Filter::from_mime_types("Images", &["IMAGE/PNG", "image/jpeg"]).unwrap();
+        assert_eq!(filter.mime_types(), ["image/png", "image/jpeg"]);
+        for extension in ["png", "jpg", "jpeg"].iter() {
+            assert!(
+                filter.extensions().iter().any(|e| e == extension),
+                "{}",
+                extension
+            );
+        }
+        assert!(filter.matches("a.JPG"));
+        assert_eq!(
+            Filter::from_spec("Images", "image/png,image/jpeg"),
+            Filter::from_mime_types("Images", &["image/png", "image/jpeg"])
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "reads the shared-mime-info database")]
+    fn mime_type_wildcards_are_expanded() {
+        let filter = Filter::from_mime_types("Images", &["image/*"]).unwrap();
+        assert_eq!(filter.mime_types(), ["image/*"]);
+        for extension in ["png", "jpg", "gif", "webp"].iter() {
+            assert!(
+                filter.extensions().iter().any(|e| e == extension),
+                "{}",
+                extension
+            );
+        }
+        assert!(!filter.matches("a.txt"));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "reads the shared-mime-info database")]
+    fn unknown_mime_types_are_rejected() {
+        assert_eq!(
+            Filter::from_mime_types("Unknown", &["application/x-nfde-unknown"]),
+            invalid(FilterError::UnknownMimeTypes)
+        );
+        // Unknown types are fine as long as one of the types is known
+        let filter =
+            Filter::from_mime_types("Text", &["application/x-nfde-unknown", "text/plain"]).unwrap();
+        assert!(filter.matches("a.txt"));
+    }
+
+    #[test]
+    fn invalid_mime_types_are_rejected() {
+        assert_eq!(
+            Filter::from_mime_types("Empty", &[] as &[&str]),
+            invalid(FilterError::NoMimeTypes)
+        );
+        for mime_type in ["image", "image/", "/png", "image/png/x", "*/*", "image/p g"].iter() {
+            assert_eq!(
+                Filter::from_mime_types("Images", &[mime_type]),
+                invalid(FilterError::MalformedMimeType),
+                "{:?}",
+                mime_type
+            );
+        }
+        assert_eq!(
+            Filter::from_mime_types("Ima\0ges", &["image/png"]),
+            Err(Error::InteriorNul)
+        );
+    }
+
+    #[test]
+    fn specs_cannot_mix_extensions_and_mime_types() {
+        assert_eq!(
+            Filter::from_spec("Images", "png,image/png"),
+            invalid(FilterError::MixedSpec)
+        );
+        assert_eq!(
+            Filter::from_spec("Images", "image/png,jpg"),
+            invalid(FilterError::MixedSpec)
+        );
+        assert_eq!(
+            Filter::from_spec("Images", "image/png,"),
+            invalid(FilterError::MalformedMimeType)
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! A catalog of common filters, which can be passed straight to `add_filter()` and `add_filters()`,
+//! e.g. `builder.add_filters([filters::IMAGES, filters::DOCUMENTS])`.
+//! `all_supported()` merges several of them into one filter, to show every supported file at once.
+
+use crate::Error;
+use crate::Filter;
+use crate::FilterError;
+use std::convert::TryInto;
+
+/// A filter with a fixed name and extensions.  Converts into a `Filter` without failing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Preset {
+    name: &'static str,
+    extensions: &'static [&'static str],
+}
+
+impl Preset {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The extensions, in lowercase and without the leading dot.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+}
+
+impl From<Preset> for Filter {
+    fn from(preset: Preset) -> Self {
+        // The presets below are known to be valid, so they need not go through `Filter::new()`
+        Filter::from_parts(
+            preset.name.to_string(),
+            preset
+                .extensions
+                .iter()
+                .map(|&extension| extension.to_string())
+                .collect(),
+            Vec::new(),
+        )
+    }
+}
+
+impl From<&Preset> for Filter {
+    fn from(preset: &Preset) -> Self {
+        Filter::from(*preset)
+    }
+}
+
+pub const IMAGES: Preset = Preset {
+    name: "Images",
+    extensions: &[
+        "png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff", "svg", "ico", "heic", "avif",
+    ],
+};
+
+pub const AUDIO: Preset = Preset {
+    name: "Audio",
+    extensions: &[
+        "mp3", "wav", "flac", "ogg", "oga", "opus", "m4a", "aac", "wma", "aiff",
+    ],
+};
+
+pub const VIDEO: Preset = Preset {
+    name: "Video",
+    extensions: &[
+        "mp4", "m4v", "mkv", "webm", "mov", "avi", "wmv", "mpg", "mpeg",
+    ],
+};
+
+pub const ARCHIVES: Preset = Preset {
+    name: "Archives",
+    extensions: &["zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar"],
+};
+
+pub const DOCUMENTS: Preset = Preset {
+    name: "Documents",
+    extensions: &["pdf", "doc", "docx", "odt", "rtf", "txt", "md"],
+};
+
+pub const SPREADSHEETS: Preset = Preset {
+    name: "Spreadsheets",
+    extensions: &["xls", "xlsx", "ods", "csv", "tsv"],
+};
+
+pub const PRESENTATIONS: Preset = Preset {
+    name: "Presentations",
+    extensions: &["ppt", "pptx", "odp", "key"],
+};
+
+pub const TEXT: Preset = Preset {
+    name: "Text files",
+    extensions: &["txt", "text", "md", "log"],
+};
+
+pub const SOURCE_CODE: Preset = Preset {
+    name: "Source code",
+    extensions: &[
+        "c", "h", "cpp", "hpp", "cc", "cxx", "rs", "go", "java", "kt", "cs", "py", "rb", "js",
+        "ts", "swift", "sh",
+    ],
+};
+
+pub const WEB: Preset = Preset {
+    name: "Web files",
+    extensions: &["html", "htm", "css", "js", "json", "xml", "svg"],
+};
+
+pub const DATA: Preset = Preset {
+    name: "Data files",
+    extensions: &["json", "xml", "yaml", "yml", "toml", "csv", "ini"],
+};
+
+pub const FONTS: Preset = Preset {
+    name: "Fonts",
+    extensions: &["ttf", "otf", "woff", "woff2"],
+};
+
+/// Every preset in this module, e.g. to build a menu of filters.
+pub const ALL: &[Preset] = &[
+    IMAGES,
+    AUDIO,
+    VIDEO,
+    ARCHIVES,
+    DOCUMENTS,
+    SPREADSHEETS,
+    PRESENTATIONS,
+    TEXT,
+    SOURCE_CODE,
+    WEB,
+    DATA,
+    FONTS,
+];
+
+/// Merges several filters (e.g. presets) into one filter named "All supported",
+/// which is typically added before the filters that it merges.
+///
+/// Fails like `combine()`.
+pub fn all_supported<F: TryInto<Filter>, I: IntoIterator<Item = F>>(
+    filters: I,
+) -> Result<Filter, Error>
+where
+    F::Error: Into<Error>,
+{
+    combine("All supported", filters)
+}
+
+/// Merges several filters (e.g. presets) into one filter with the given name.
+/// Extensions that appear in more than one filter are only listed once.
+/// MIME types are kept only if every merged filter has them, since a partial list would hide files.
+///
+/// Fails if one of the filters is invalid, or if there are no filters.
+pub fn combine<N: Into<String>, F: TryInto<Filter>, I: IntoIterator<Item = F>>(
+    name: N,
+    filters: I,
+) -> Result<Filter, Error>
+where
+    F::Error: Into<Error>,
+{
+    let name = name.into();
+    if name.contains('\0') {
+        return Err(Error::InteriorNul);
+    }
+    let mut extensions: Vec<String> = Vec::new();
+    let mut mime_types: Vec<String> = Vec::new();
+    let mut all_have_mime_types = true;
+    for filter in filters {
+        let filter: Filter = filter.try_into().map_err(Into::into)?;
+        for extension in filter.extensions() {
+            if !extensions.contains(extension) {
+                extensions.push(extension.clone());
+            }
+        }
+        all_have_mime_types &= !filter.mime_types().is_empty();
+        for mime_type in filter.mime_types() {
+            if !mime_types.contains(mime_type) {
+                mime_types.push(mime_type.clone());
+            }
+        }
+    }
+    if extensions.is_empty() {
+        return Err(Error::InvalidFilter(FilterError::NoExtensions));
+    }
+    if !all_have_mime_types {
+        mime_types.clear();
+    }
+    Ok(Filter::from_parts(name, extensions, mime_types))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combine_lists_each_extension_once() {
+        let filter = combine("Web and data", [WEB, DATA]).unwrap();
+        assert_eq!(filter.name(), "Web and data");
+        assert_eq!(
+            filter.extensions(),
+            [
+                "html", "htm", "css", "js", "json", "xml", "svg", "yaml", "yml", "toml", "csv",
+                "ini"
+            ]
+        );
+        assert!(filter.mime_types().is_empty());
+    }
+
+    #[test]
+    fn combine_validates_the_filters() {
+        assert_eq!(
+            combine("Mixed", vec![("Text", "txt"), ("Images", "*.png")]),
+            Err(Error::InvalidFilter(FilterError::ExtensionWildcard))
+        );
+        assert_eq!(combine("Te\0xt", [TEXT]), Err(Error::InteriorNul));
+    }
+
+    #[test]
+    fn combine_rejects_empty_input() {
+        assert_eq!(
+            combine("Nothing", &[] as &[Preset]),
+            Err(Error::InvalidFilter(FilterError::NoExtensions))
+        );
+    }
+
+    #[test]
+    fn combine_keeps_mime_types_only_if_every_filter_has_them() {
+        let png = Filter::from_parts("PNG".into(), vec!["png".into()], vec!["image/png".into()]);
+        let jpeg = Filter::from_parts(
+            "JPEG".into(),
+            vec!["jpg".into(), "jpeg".into()],
+            vec!["image/jpeg".into()],
+        );
+        let filter = combine("Images", vec![png.clone(), jpeg, png.clone()]).unwrap();
+        assert_eq!(filter.extensions(), ["png", "jpg", "jpeg"]);
+        assert_eq!(filter.mime_types(), ["image/png", "image/jpeg"]);
+        let filter = combine("Images", vec![png, Filter::from(IMAGES)]).unwrap();
+        assert!(filter.mime_types().is_empty());
+    }
+
+    #[test]
+    fn all_supported_lists_the_extensions_of_every_filter_in_order() {
+        let filter = all_supported([IMAGES, DOCUMENTS]).unwrap();
+        assert_eq!(filter.name(), "All supported");
+        let expected: Vec<&str> = IMAGES
+            .extensions()
+            .iter()
+            .chain(DOCUMENTS.extensions())
+            .copied()
+            .collect();
+        assert_eq!(filter.extensions(), expected.as_slice());
+    }
+}
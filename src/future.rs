@@ -0,0 +1,116 @@
+use crate::backend::Backend;
+use crate::DialogResult;
+use crate::Error;
+use crate::Nfd;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+
+/// A future that resolves to the result of a dialog shown on a dedicated thread.
+///
+/// This is returned by `show_async()`.  It does not depend on any particular async runtime;
+/// the dialog thread wakes the task that last polled this future once the dialog is closed.
+/// Dropping the future does not close the dialog, it only discards the result.
+pub struct DialogFuture<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+// The result crosses threads as data that is `Send` (e.g. owned paths instead of an NFDe path set),
+// and is turned into the output on the thread that polls the future
+type Finish<T> = Box<dyn FnOnce() -> T + Send>;
+
+struct Shared<T> {
+    result: Option<Finish<T>>,
+    finished: bool,
+    waker: Option<Waker>,
+}
+
+// Marks the dialog as finished and wakes the waiting task, even if the dialog thread panics
+struct Completion<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut shared = match self.shared.lock() {
+                Ok(shared) => shared,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            shared.finished = true;
+            shared.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T: 'static> DialogFuture<DialogResult<T>> {
+    /// Runs `show` on a new thread that initializes `backend` for itself,
+    /// since NFDe requires the dialog to be shown on the thread that called `NFD_Init`.
+    /// `show` must return data that does not depend on the backend staying initialized,
+    /// which `finish` turns into the output on the thread that polls the future.
+    pub(crate) fn spawn<S, F, G>(backend: Arc<dyn Backend>, show: F, finish: G) -> Self
+    where
+        S: Send + 'static,
+        F: FnOnce() -> DialogResult<S> + Send + 'static,
+        G: FnOnce(S) -> T + Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            result: None,
+            finished: false,
+            waker: None,
+        }));
+        let completion = Completion {
+            shared: shared.clone(),
+        };
+        std::thread::spawn(move || {
+            let result = match Nfd::with_backend(backend) {
+                Ok(_nfd) => show(),
+                Err(error) => DialogResult::Err(error),
+            };
+            if let Ok(mut shared) = completion.shared.lock() {
+                shared.result = Some(Box::new(move || result.map(finish)));
+            }
+            drop(completion);
+        });
+        Self { shared }
+    }
+
+    /// Returns a future that has already failed, e.g. when the dialog could not be shown at all.
+    pub(crate) fn failed(error: Error) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                result: Some(Box::new(move || DialogResult::Err(error))),
+                finished: true,
+                waker: None,
+            })),
+        }
+    }
+}
+
+impl<T> Future for DialogFuture<T> {
+    type Output = T;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = {
+            let mut shared = match self.shared.lock() {
+                Ok(shared) => shared,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            match shared.result.take() {
+                Some(result) => result,
+                None if shared.finished => panic!("The dialog thread panicked"),
+                None => {
+                    shared.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        };
+        Poll::Ready(result())
+    }
+}
@@ -1,22 +1,79 @@
-use std::ffi::CStr;
+use std::cell::RefCell;
+use std::convert::TryInto;
 use std::iter::IntoIterator;
+use std::marker::PhantomData;
 use std::path::Path;
+use std::path::PathBuf;
 use std::result::Result;
+use std::sync::Arc;
 
+pub mod backend;
+mod error;
 mod ffi;
+mod filter;
+pub mod filters;
+mod future;
+mod mime;
+mod native_filters;
 mod path;
+#[cfg(test)]
+mod test_alloc;
 
-pub struct Nfd {}
+use backend::Backend;
+use backend::BackendKind;
+use backend::BackendSelection;
+use backend::DialogKind;
+use backend::DialogOptions;
+use backend::ParentWindow;
+
+// Counts the bytes allocated by each thread, for the tests that check for leaks
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: test_alloc::CountingAllocator = test_alloc::CountingAllocator;
+
+/// A handle to an initialized NFD.
+///
+/// With the native backend, NFD is reference-counted per thread: the first `Nfd` on a thread
+/// initializes NFDe, and dropping the last one deinitializes it.  Since NFDe must be initialized,
+/// used and deinitialized on the same thread, `Nfd` is neither `Send` nor `Sync`.
+/// On Linux, GTK can only be used by one thread, so NFDe is used on a dedicated thread that shows the dialogs
+/// of every thread, one at a time.  On macOS, AppKit can only be used by the main thread, so while a thread holds
+/// an `Nfd` with the native backend, creating one on another thread fails with `Error::WrongThread`.
+pub struct Nfd {
+    backend: Arc<dyn Backend>,
+    selection: Option<BackendSelection>,
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Chooses the backend of an `Nfd` by trying several in order.  See `Nfd::builder()`.
+#[derive(Clone, Debug)]
+pub struct NfdBuilder {
+    backends: Vec<BackendKind>,
+}
 
 pub use path::NfdPathBuf;
 pub struct NfdPathSetBuf {
-    ptr: *mut ffi::nfdpathset_t,
+    set: PathSetRepr,
+}
+// A path set is either allocated by NFDe, or owned by Rust (e.g. when it comes from a non-native backend)
+enum PathSetRepr {
+    #[cfg(nfd_native)]
+    Native {
+        ptr: *mut ffi::nfdpathset_t,
+        // NFDe path sets are immutable, so the number of paths is queried once
+        len: usize,
+    },
+    Owned(Vec<PathBuf>),
 }
 pub use path::NfdPathSetPathBuf;
 
-pub type Error = &'static str;
+pub use error::Error;
+pub use error::FilterError;
+pub use filter::Filter;
+pub use future::DialogFuture;
 pub type InitResult = Result<Nfd, Error>;
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DialogResult<T> {
     Ok(T),
     Cancel,
@@ -25,82 +82,369 @@ pub enum DialogResult<T> {
 pub type SingleFileResult = DialogResult<NfdPathBuf>;
 pub type MultipleFileResult = DialogResult<NfdPathSetBuf>;
 
+/// What the user selected in a dialog with filters, and which filter was selected at the time.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Selection<T> {
+    pub value: T,
+    /// The index of the selected filter, in the order the filters were added.
+    /// `None` if there are no filters, if a filter that was not added (e.g. "All files") was selected,
+    /// or if the backend cannot tell and the extension of the selection matches none of the filters.
+    pub filter_index: Option<usize>,
+}
+pub type SingleFileSelection = DialogResult<Selection<NfdPathBuf>>;
+pub type MultipleFileSelection = DialogResult<Selection<NfdPathSetBuf>>;
+
 pub trait SingleFileDialogBuilder {
     fn show(&self) -> SingleFileResult;
+
+    /// Shows the dialog on a dedicated thread without blocking the calling thread.
+    /// The returned future can be awaited on any async runtime.
+    ///
+    /// Fails with `Error::WrongThread` if the backend cannot show dialogs on another thread
+    /// (NFDe on macOS, where AppKit only allows dialogs on the main thread).
+    fn show_async(self) -> DialogFuture<SingleFileResult>
+    where
+        Self: Sized;
 }
 pub trait MultipleFileDialogBuilder {
     fn show(&self) -> MultipleFileResult;
+
+    /// Shows the dialog on a dedicated thread without blocking the calling thread.
+    /// The returned future can be awaited on any async runtime.
+    ///
+    /// Fails with `Error::WrongThread` if the backend cannot show dialogs on another thread
+    /// (NFDe on macOS, where AppKit only allows dialogs on the main thread).
+    fn show_async(self) -> DialogFuture<MultipleFileResult>
+    where
+        Self: Sized;
 }
 
 pub trait DefaultPathDialogBuilder {
-    fn default_path<'a, P: AsRef<Path>>(&'a mut self, path: P) -> Result<&'a mut Self, Error>;
+    fn default_path<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error>;
 }
 
+/// Makes the dialog modal to a window of the application.
+#[cfg(feature = "raw-window-handle")]
+pub trait ParentWindowDialogBuilder {
+    fn parent_window<W: raw_window_handle::HasWindowHandle + ?Sized>(
+        &mut self,
+        window: &W,
+    ) -> Result<&mut Self, Error>;
+}
+
+/// Restricts the files that the dialog shows.
+///
+/// Filters are either `Filter`s, or `(name, spec)` string pairs such as `("Source code", "c,cpp,cc")`,
+/// which are validated in the same way as `Filter::from_spec()`.
 pub trait FilterableDialogBuilder {
-    fn add_filter<'a>(&'a mut self, name: &str, spec: &str) -> Result<&'a mut Self, Error>;
-    fn add_filters<'a, 'b, 'c, I: Iterator<Item = (&'b str, &'c str)>>(
-        &'a mut self,
+    fn add_filter<F: TryInto<Filter>>(&mut self, filter: F) -> Result<&mut Self, Error>
+    where
+        F::Error: Into<Error>;
+    fn add_filters<F: TryInto<Filter>, I: IntoIterator<Item = F>>(
+        &mut self,
         filters: I,
-    ) -> Result<&'a mut Self, Error>;
+    ) -> Result<&mut Self, Error>
+    where
+        F::Error: Into<Error>;
+    /// Makes `show()` check that the selected paths match at least one of the filters,
+    /// since some dialogs let the user pick or type a name that does not.
+    /// If any path does not match, `show()` returns `Error::FilterMismatch` with those paths.
+    /// Nothing is checked if there are no filters.  Off by default.
+    fn validate_selection(&mut self, validate: bool) -> &mut Self;
+    /// Sets the filter that is selected when the dialog opens, by its index in the order the filters were added.
+    /// The first filter is used if there is no filter at this index when the dialog is shown.
+    ///
+    /// Note: NFDe and the `zenity`/`kdialog` backend always select the first filter,
+    /// so with them the selected filter is moved to the front of the list.
+    fn default_filter(&mut self, index: usize) -> &mut Self;
+}
+
+/// The backend that a builder shows its dialog with: the backend of the `Nfd` that created it,
+/// or, for builders created with `new()`, the backend of the most recent `Nfd` on the thread that shows the dialog.
+#[derive(Clone, Default)]
+struct BuilderBackend(Option<Arc<dyn Backend>>);
+
+thread_local! {
+    // The backends of the live `Nfd`s on this thread, in the order they were created
+    static CURRENT_BACKENDS: RefCell<Vec<Arc<dyn Backend>>> = const { RefCell::new(Vec::new()) };
 }
 
+/// Dialog builders hold plain Rust data, so they can be cloned, sent to other threads and kept for later.
+/// They can be created from an `Nfd` (e.g. `nfd.open_file()`), or without one with `new()`.
+#[derive(Clone, Debug, Default)]
 pub struct OpenFileDialogBuilder {
-    filters: Vec<ffi::nfdnfilteritem_t>,
-    default_path: Option<path::NfdCString>,
+    backend: BuilderBackend,
+    filters: Vec<Filter>,
+    validate_selection: bool,
+    default_filter: usize,
+    default_path: Option<PathBuf>,
+    parent_window: Option<ParentWindow>,
 }
+#[derive(Clone, Debug, Default)]
 pub struct OpenFileMultipleDialogBuilder {
-    filters: Vec<ffi::nfdnfilteritem_t>,
-    default_path: Option<path::NfdCString>,
+    backend: BuilderBackend,
+    filters: Vec<Filter>,
+    validate_selection: bool,
+    default_filter: usize,
+    default_path: Option<PathBuf>,
+    parent_window: Option<ParentWindow>,
 }
+#[derive(Clone, Debug, Default)]
 pub struct SaveFileDialogBuilder {
-    filters: Vec<ffi::nfdnfilteritem_t>,
-    default_path: Option<path::NfdCString>,
-    default_name: Option<path::NfdCString>,
+    backend: BuilderBackend,
+    filters: Vec<Filter>,
+    validate_selection: bool,
+    default_filter: usize,
+    default_path: Option<PathBuf>,
+    default_name: Option<PathBuf>,
+    append_extension: bool,
+    overwrite_policy: OverwritePolicy,
+    missing_parent_policy: MissingParentPolicy,
+    parent_window: Option<ParentWindow>,
+}
+
+/// What `SaveFileDialogBuilder::show()` does when the chosen file already exists.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum OverwritePolicy {
+    /// Asks the user to confirm replacing the file.  Native dialogs ask themselves; other backends emulate it.
+    #[default]
+    Confirm,
+    /// Does not ask.  Some native dialogs ask anyway, since they cannot be told not to.
+    Allow,
+    /// Fails with `Error::FileExists`.
+    Forbid,
+}
+
+/// What `SaveFileDialogBuilder::show()` does when the folder of the chosen file does not exist
+/// (e.g. when the user typed "new/report.pdf").
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum MissingParentPolicy {
+    /// Returns the path as it is.
+    #[default]
+    Allow,
+    /// Fails with `Error::MissingParent`.
+    Forbid,
+    /// Creates the missing folders, or fails with `Error::CreateParent` if that fails.
+    Create,
 }
+
+/// What a dialog builder shows, as plain data (e.g. to remember the last settings of a dialog).
+/// Get it from a builder with `config()`, and give it back to a builder with `apply_config()`.
+///
+/// The backend, parent window, validation and save policies of the builder are not included.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DialogConfig {
+    /// The kind of builder that the configuration was taken from.  `apply_config()` does not check it.
+    pub kind: DialogKind,
+    /// Always empty for folder dialogs.
+    pub filters: Vec<Filter>,
+    pub default_filter: usize,
+    pub default_path: Option<PathBuf>,
+    /// Only used by save dialogs.
+    pub default_name: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct PickFolderDialogBuilder {
-    default_path: Option<path::NfdCString>,
+    backend: BuilderBackend,
+    default_path: Option<PathBuf>,
+    parent_window: Option<ParentWindow>,
+}
+#[derive(Clone, Debug, Default)]
+pub struct PickFolderMultipleDialogBuilder {
+    backend: BuilderBackend,
+    default_path: Option<PathBuf>,
+    parent_window: Option<ParentWindow>,
+}
+
+impl OpenFileDialogBuilder {
+    /// Creates a builder that is not tied to an `Nfd`.  Its dialog is shown with the backend
+    /// of the most recent `Nfd` on the thread that shows it, or fails with `Error::NotInitialized` if there is none.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl OpenFileMultipleDialogBuilder {
+    /// Creates a builder that is not tied to an `Nfd`, like `OpenFileDialogBuilder::new()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl SaveFileDialogBuilder {
+    /// Creates a builder that is not tied to an `Nfd`, like `OpenFileDialogBuilder::new()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl PickFolderDialogBuilder {
+    /// Creates a builder that is not tied to an `Nfd`, like `OpenFileDialogBuilder::new()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl PickFolderMultipleDialogBuilder {
+    /// Creates a builder that is not tied to an `Nfd`, like `OpenFileDialogBuilder::new()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
 }
-unsafe impl Send for OpenFileDialogBuilder {}
-unsafe impl Send for OpenFileMultipleDialogBuilder {}
-unsafe impl Send for SaveFileDialogBuilder {}
-unsafe impl Send for PickFolderDialogBuilder {}
 
 impl Nfd {
+    /// Initializes NFD with the first backend of `BackendKind::available()` that can be initialized.
+    /// Which one was chosen, and why the others were skipped, is available from `backend_selection()`.
     pub fn new() -> InitResult {
-        let res = unsafe { ffi::NFD_Init() };
-        wrap_init_result(res)
+        Self::builder().init()
+    }
+    /// Returns a builder that tries the backends in a custom order.
+    pub fn builder() -> NfdBuilder {
+        NfdBuilder::default()
+    }
+    /// Initializes NFD with a custom backend (e.g. `backend::mock::MockBackend` for testing).
+    /// All builders created from the returned `Nfd` show their dialogs with this backend.
+    pub fn with_backend(backend: Arc<dyn Backend>) -> InitResult {
+        backend.init()?;
+        CURRENT_BACKENDS.with(|backends| backends.borrow_mut().push(backend.clone()));
+        Ok(Nfd {
+            backend,
+            selection: None,
+            _not_send: PhantomData,
+        })
+    }
+    /// Returns which backend was chosen by `new()` or `NfdBuilder::init()`, and why the backends before it were skipped.
+    /// Returns `None` if the backend was installed with `with_backend()`.
+    pub fn backend_selection(&self) -> Option<&BackendSelection> {
+        self.selection.as_ref()
     }
     pub fn open_file(&self) -> OpenFileDialogBuilder {
         OpenFileDialogBuilder {
-            filters: Vec::new(),
-            default_path: None,
+            backend: BuilderBackend(Some(self.backend.clone())),
+            ..Default::default()
         }
     }
     pub fn open_file_multiple(&self) -> OpenFileMultipleDialogBuilder {
         OpenFileMultipleDialogBuilder {
-            filters: Vec::new(),
-            default_path: None,
+            backend: BuilderBackend(Some(self.backend.clone())),
+            ..Default::default()
         }
     }
     pub fn save_file(&self) -> SaveFileDialogBuilder {
         SaveFileDialogBuilder {
-            filters: Vec::new(),
-            default_path: None,
-            default_name: None,
+            backend: BuilderBackend(Some(self.backend.clone())),
+            ..Default::default()
         }
     }
     pub fn pick_folder(&self) -> PickFolderDialogBuilder {
-        PickFolderDialogBuilder { default_path: None }
+        PickFolderDialogBuilder {
+            backend: BuilderBackend(Some(self.backend.clone())),
+            ..Default::default()
+        }
+    }
+    pub fn pick_folder_multiple(&self) -> PickFolderMultipleDialogBuilder {
+        PickFolderMultipleDialogBuilder {
+            backend: BuilderBackend(Some(self.backend.clone())),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for NfdBuilder {
+    fn default() -> Self {
+        NfdBuilder {
+            backends: BackendKind::available(),
+        }
+    }
+}
+
+impl NfdBuilder {
+    /// Sets the backends to try, in order (e.g. to prefer the terminal over the native dialogs).
+    /// Backends that are not available in this build fail to initialize, and are skipped.
+    pub fn backends<I: IntoIterator<Item = BackendKind>>(&mut self, backends: I) -> &mut Self {
+        self.backends = backends.into_iter().collect();
+        self
+    }
+    /// Initializes the first backend that succeeds.
+    /// If none does, returns `Error::NoBackend` with the error of each backend.
+    pub fn init(&self) -> InitResult {
+        let mut skipped = Vec::new();
+        for &kind in &self.backends {
+            match kind.create().and_then(Nfd::with_backend) {
+                Ok(mut nfd) => {
+                    nfd.selection = Some(BackendSelection {
+                        chosen: kind,
+                        skipped,
+                    });
+                    return Ok(nfd);
+                }
+                Err(error) => skipped.push((kind, error)),
+            }
+        }
+        Err(Error::NoBackend(skipped))
     }
 }
 
 impl Drop for Nfd {
     fn drop(&mut self) {
-        unsafe { ffi::NFD_Quit() };
+        CURRENT_BACKENDS.with(|backends| {
+            let mut backends = backends.borrow_mut();
+            if let Some(index) = backends
+                .iter()
+                .rposition(|backend| Arc::ptr_eq(backend, &self.backend))
+            {
+                backends.remove(index);
+            }
+        });
+        self.backend.quit();
+    }
+}
+
+impl BuilderBackend {
+    fn get(&self) -> Result<Arc<dyn Backend>, Error> {
+        match &self.0 {
+            Some(backend) => Ok(backend.clone()),
+            None => CURRENT_BACKENDS
+                .with(|backends| backends.borrow().last().cloned())
+                .ok_or(Error::NotInitialized),
+        }
+    }
+
+    /// Runs `show` on a dialog thread, which needs the backend to be resolved on the calling thread first.
+    /// See `DialogFuture::spawn()` for `finish`.
+    fn spawn<S, T, F, G>(&self, show: F, finish: G) -> DialogFuture<DialogResult<T>>
+    where
+        S: Send + 'static,
+        T: 'static,
+        F: FnOnce() -> DialogResult<S> + Send + 'static,
+        G: FnOnce(S) -> T + Send + 'static,
+    {
+        match self.get() {
+            Ok(backend) if !backend.supports_dialog_thread() => {
+                DialogFuture::failed(Error::WrongThread)
+            }
+            Ok(backend) => DialogFuture::spawn(backend, show, finish),
+            Err(error) => DialogFuture::failed(error),
+        }
+    }
+}
+
+impl std::fmt::Debug for BuilderBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("BuilderBackend(Nfd)"),
+            None => f.write_str("BuilderBackend(Current)"),
+        }
     }
 }
 
 impl<T> DialogResult<T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> DialogResult<U> {
+        match self {
+            DialogResult::Ok(val) => DialogResult::Ok(f(val)),
+            DialogResult::Cancel => DialogResult::Cancel,
+            DialogResult::Err(error) => DialogResult::Err(error),
+        }
+    }
     pub fn into_result(self) -> Result<Option<T>, Error> {
         match self {
             DialogResult::Ok(val) => Ok(Some(val)),
@@ -112,221 +456,735 @@ impl<T> DialogResult<T> {
         match self {
             DialogResult::Ok(val) => Ok(Some(val)),
             DialogResult::Cancel => Ok(None),
-            DialogResult::Err(error) => Err(error),
+            DialogResult::Err(error) => Err(error.clone()),
         }
     }
 }
 
 impl DefaultPathDialogBuilder for OpenFileDialogBuilder {
-    fn default_path<'a, P: AsRef<Path>>(&'a mut self, path: P) -> Result<&'a mut Self, Error> {
-        self.default_path = Some(path::unwrap_path(path.as_ref())?);
+    fn default_path<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error> {
+        path::unwrap_path(path.as_ref())?;
+        self.default_path = Some(path.as_ref().to_path_buf());
         Ok(self)
     }
 }
 impl DefaultPathDialogBuilder for OpenFileMultipleDialogBuilder {
-    fn default_path<'a, P: AsRef<Path>>(&'a mut self, path: P) -> Result<&'a mut Self, Error> {
-        self.default_path = Some(path::unwrap_path(path.as_ref())?);
+    fn default_path<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error> {
+        path::unwrap_path(path.as_ref())?;
+        self.default_path = Some(path.as_ref().to_path_buf());
         Ok(self)
     }
 }
 impl DefaultPathDialogBuilder for SaveFileDialogBuilder {
-    fn default_path<'a, P: AsRef<Path>>(&'a mut self, path: P) -> Result<&'a mut Self, Error> {
-        self.default_path = Some(path::unwrap_path(path.as_ref())?);
+    fn default_path<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error> {
+        path::unwrap_path(path.as_ref())?;
+        self.default_path = Some(path.as_ref().to_path_buf());
         Ok(self)
     }
 }
 impl DefaultPathDialogBuilder for PickFolderDialogBuilder {
-    fn default_path<'a, P: AsRef<Path>>(&'a mut self, path: P) -> Result<&'a mut Self, Error> {
-        self.default_path = Some(path::unwrap_path(path.as_ref())?);
+    fn default_path<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error> {
+        path::unwrap_path(path.as_ref())?;
+        self.default_path = Some(path.as_ref().to_path_buf());
+        Ok(self)
+    }
+}
+impl DefaultPathDialogBuilder for PickFolderMultipleDialogBuilder {
+    fn default_path<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error> {
+        path::unwrap_path(path.as_ref())?;
+        self.default_path = Some(path.as_ref().to_path_buf());
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "raw-window-handle")]
+impl ParentWindowDialogBuilder for OpenFileDialogBuilder {
+    fn parent_window<W: raw_window_handle::HasWindowHandle + ?Sized>(
+        &mut self,
+        window: &W,
+    ) -> Result<&mut Self, Error> {
+        self.parent_window = Some(ParentWindow::from_window_handle(window)?);
+        Ok(self)
+    }
+}
+#[cfg(feature = "raw-window-handle")]
+impl ParentWindowDialogBuilder for OpenFileMultipleDialogBuilder {
+    fn parent_window<W: raw_window_handle::HasWindowHandle + ?Sized>(
+        &mut self,
+        window: &W,
+    ) -> Result<&mut Self, Error> {
+        self.parent_window = Some(ParentWindow::from_window_handle(window)?);
+        Ok(self)
+    }
+}
+#[cfg(feature = "raw-window-handle")]
+impl ParentWindowDialogBuilder for SaveFileDialogBuilder {
+    fn parent_window<W: raw_window_handle::HasWindowHandle + ?Sized>(
+        &mut self,
+        window: &W,
+    ) -> Result<&mut Self, Error> {
+        self.parent_window = Some(ParentWindow::from_window_handle(window)?);
+        Ok(self)
+    }
+}
+#[cfg(feature = "raw-window-handle")]
+impl ParentWindowDialogBuilder for PickFolderDialogBuilder {
+    fn parent_window<W: raw_window_handle::HasWindowHandle + ?Sized>(
+        &mut self,
+        window: &W,
+    ) -> Result<&mut Self, Error> {
+        self.parent_window = Some(ParentWindow::from_window_handle(window)?);
+        Ok(self)
+    }
+}
+#[cfg(feature = "raw-window-handle")]
+impl ParentWindowDialogBuilder for PickFolderMultipleDialogBuilder {
+    fn parent_window<W: raw_window_handle::HasWindowHandle + ?Sized>(
+        &mut self,
+        window: &W,
+    ) -> Result<&mut Self, Error> {
+        self.parent_window = Some(ParentWindow::from_window_handle(window)?);
         Ok(self)
     }
 }
 
 impl FilterableDialogBuilder for OpenFileDialogBuilder {
-    fn add_filter<'a>(&'a mut self, name: &str, spec: &str) -> Result<&'a mut Self, Error> {
-        self.filters.push(make_filter(name, spec)?);
+    fn add_filter<F: TryInto<Filter>>(&mut self, filter: F) -> Result<&mut Self, Error>
+    where
+        F::Error: Into<Error>,
+    {
+        self.filters.push(make_filter(filter)?);
         Ok(self)
     }
-    fn add_filters<'a, 'b, 'c, I: Iterator<Item = (&'b str, &'c str)>>(
-        &'a mut self,
+    fn add_filters<F: TryInto<Filter>, I: IntoIterator<Item = F>>(
+        &mut self,
         filters: I,
-    ) -> Result<&'a mut Self, Error> {
+    ) -> Result<&mut Self, Error>
+    where
+        F::Error: Into<Error>,
+    {
         self.filters.append(&mut make_filters(filters)?);
         Ok(self)
     }
+    fn validate_selection(&mut self, validate: bool) -> &mut Self {
+        self.validate_selection = validate;
+        self
+    }
+    fn default_filter(&mut self, index: usize) -> &mut Self {
+        self.default_filter = index;
+        self
+    }
 }
 impl FilterableDialogBuilder for OpenFileMultipleDialogBuilder {
-    fn add_filter<'a>(&'a mut self, name: &str, spec: &str) -> Result<&'a mut Self, Error> {
-        self.filters.push(make_filter(name, spec)?);
+    fn add_filter<F: TryInto<Filter>>(&mut self, filter: F) -> Result<&mut Self, Error>
+    where
+        F::Error: Into<Error>,
+    {
+        self.filters.push(make_filter(filter)?);
         Ok(self)
     }
-    fn add_filters<'a, 'b, 'c, I: Iterator<Item = (&'b str, &'c str)>>(
-        &'a mut self,
+    fn add_filters<F: TryInto<Filter>, I: IntoIterator<Item = F>>(
+        &mut self,
         filters: I,
-    ) -> Result<&'a mut Self, Error> {
+    ) -> Result<&mut Self, Error>
+    where
+        F::Error: Into<Error>,
+    {
         self.filters.append(&mut make_filters(filters)?);
         Ok(self)
     }
+    fn validate_selection(&mut self, validate: bool) -> &mut Self {
+        self.validate_selection = validate;
+        self
+    }
+    fn default_filter(&mut self, index: usize) -> &mut Self {
+        self.default_filter = index;
+        self
+    }
 }
 impl FilterableDialogBuilder for SaveFileDialogBuilder {
-    fn add_filter<'a>(&'a mut self, name: &str, spec: &str) -> Result<&'a mut Self, Error> {
-        self.filters.push(make_filter(name, spec)?);
+    fn add_filter<F: TryInto<Filter>>(&mut self, filter: F) -> Result<&mut Self, Error>
+    where
+        F::Error: Into<Error>,
+    {
+        self.filters.push(make_filter(filter)?);
         Ok(self)
     }
-    fn add_filters<'a, 'b, 'c, I: Iterator<Item = (&'b str, &'c str)>>(
-        &'a mut self,
+    fn add_filters<F: TryInto<Filter>, I: IntoIterator<Item = F>>(
+        &mut self,
         filters: I,
-    ) -> Result<&'a mut Self, Error> {
+    ) -> Result<&mut Self, Error>
+    where
+        F::Error: Into<Error>,
+    {
         self.filters.append(&mut make_filters(filters)?);
         Ok(self)
     }
+    fn validate_selection(&mut self, validate: bool) -> &mut Self {
+        self.validate_selection = validate;
+        self
+    }
+    fn default_filter(&mut self, index: usize) -> &mut Self {
+        self.default_filter = index;
+        self
+    }
+}
+
+impl OpenFileDialogBuilder {
+    pub fn config(&self) -> DialogConfig {
+        DialogConfig {
+            kind: DialogKind::OpenFile,
+            filters: self.filters.clone(),
+            default_filter: self.default_filter,
+            default_path: self.default_path.clone(),
+            default_name: None,
+        }
+    }
+    /// Replaces the filters, default filter and default path with those of `config`.
+    /// Fails with `Error::InteriorNul`, leaving the builder unchanged, if a path cannot be passed to NFDe.
+    pub fn apply_config(&mut self, config: &DialogConfig) -> Result<&mut Self, Error> {
+        check_config(config)?;
+        self.filters = config.filters.clone();
+        self.default_filter = config.default_filter;
+        self.default_path = config.default_path.clone();
+        Ok(self)
+    }
+}
+impl OpenFileMultipleDialogBuilder {
+    pub fn config(&self) -> DialogConfig {
+        DialogConfig {
+            kind: DialogKind::OpenFileMultiple,
+            filters: self.filters.clone(),
+            default_filter: self.default_filter,
+            default_path: self.default_path.clone(),
+            default_name: None,
+        }
+    }
+    /// Replaces the filters, default filter and default path with those of `config`, like `OpenFileDialogBuilder::apply_config()`.
+    pub fn apply_config(&mut self, config: &DialogConfig) -> Result<&mut Self, Error> {
+        check_config(config)?;
+        self.filters = config.filters.clone();
+        self.default_filter = config.default_filter;
+        self.default_path = config.default_path.clone();
+        Ok(self)
+    }
+}
+impl SaveFileDialogBuilder {
+    pub fn config(&self) -> DialogConfig {
+        DialogConfig {
+            kind: DialogKind::SaveFile,
+            filters: self.filters.clone(),
+            default_filter: self.default_filter,
+            default_path: self.default_path.clone(),
+            default_name: self.default_name.clone(),
+        }
+    }
+    /// Replaces the filters, default filter, default path and default name with those of `config`,
+    /// like `OpenFileDialogBuilder::apply_config()`.
+    pub fn apply_config(&mut self, config: &DialogConfig) -> Result<&mut Self, Error> {
+        check_config(config)?;
+        self.filters = config.filters.clone();
+        self.default_filter = config.default_filter;
+        self.default_path = config.default_path.clone();
+        self.default_name = config.default_name.clone();
+        Ok(self)
+    }
+}
+impl PickFolderDialogBuilder {
+    pub fn config(&self) -> DialogConfig {
+        DialogConfig {
+            kind: DialogKind::PickFolder,
+            filters: Vec::new(),
+            default_filter: 0,
+            default_path: self.default_path.clone(),
+            default_name: None,
+        }
+    }
+    /// Replaces the default path with that of `config`, like `OpenFileDialogBuilder::apply_config()`.
+    pub fn apply_config(&mut self, config: &DialogConfig) -> Result<&mut Self, Error> {
+        check_config(config)?;
+        self.default_path = config.default_path.clone();
+        Ok(self)
+    }
+}
+impl PickFolderMultipleDialogBuilder {
+    pub fn config(&self) -> DialogConfig {
+        DialogConfig {
+            kind: DialogKind::PickFolderMultiple,
+            filters: Vec::new(),
+            default_filter: 0,
+            default_path: self.default_path.clone(),
+            default_name: None,
+        }
+    }
+    /// Replaces the default path with that of `config`, like `OpenFileDialogBuilder::apply_config()`.
+    pub fn apply_config(&mut self, config: &DialogConfig) -> Result<&mut Self, Error> {
+        check_config(config)?;
+        self.default_path = config.default_path.clone();
+        Ok(self)
+    }
+}
+/// The filters of a configuration are already valid, but its paths may not be (e.g. after deserializing).
+fn check_config(config: &DialogConfig) -> Result<(), Error> {
+    for path in config.default_path.iter().chain(&config.default_name) {
+        path::unwrap_path(path)?;
+    }
+    Ok(())
 }
 
 impl SaveFileDialogBuilder {
     pub fn default_name<'a, P: AsRef<Path>>(&'a mut self, name: &P) -> Result<&'a mut Self, Error> {
-        self.default_name = Some(path::unwrap_path(name.as_ref())?);
+        path::unwrap_path(name.as_ref())?;
+        self.default_name = Some(name.as_ref().to_path_buf());
         Ok(self)
     }
+    /// Sets what `show()` does when the chosen file already exists.  `OverwritePolicy::Confirm` by default.
+    pub fn overwrite_policy(&mut self, policy: OverwritePolicy) -> &mut Self {
+        self.overwrite_policy = policy;
+        self
+    }
+    /// Sets what `show()` does when the folder of the chosen file does not exist.
+    /// `MissingParentPolicy::Allow` by default.
+    pub fn missing_parent_policy(&mut self, policy: MissingParentPolicy) -> &mut Self {
+        self.missing_parent_policy = policy;
+        self
+    }
+    /// Makes `show()` append the first extension of the selected filter (e.g. "report" becomes "report.pdf")
+    /// if the name chosen by the user matches none of the filters.
+    /// If the backend cannot tell which filter was selected, the default filter is used.
+    /// Some backends already do this and others do not, so this makes the result the same on every platform.
+    /// Off by default.
+    pub fn append_extension(&mut self, append: bool) -> &mut Self {
+        self.append_extension = append;
+        self
+    }
+}
+
+impl OpenFileDialogBuilder {
+    /// Like `show()`, but also returns which filter was selected.
+    pub fn show_with_filter(&self) -> SingleFileSelection {
+        let backend = match self.backend.get() {
+            Ok(backend) => backend,
+            Err(error) => return DialogResult::Err(error),
+        };
+        let res = backend.open_file(&DialogOptions {
+            filters: &self.filters,
+            default_filter: default_filter_index(&self.filters, self.default_filter),
+            default_path: self.default_path.as_deref(),
+            parent_window: self.parent_window,
+            ..Default::default()
+        });
+        validate_single(self.validate_selection, &self.filters, res)
+    }
+}
+impl SaveFileDialogBuilder {
+    /// Like `show()`, but also returns which filter was selected (e.g. to choose the format to save in).
+    pub fn show_with_filter(&self) -> SingleFileSelection {
+        let backend = match self.backend.get() {
+            Ok(backend) => backend,
+            Err(error) => return DialogResult::Err(error),
+        };
+        let default_filter = default_filter_index(&self.filters, self.default_filter);
+        let mut default_path = self.default_path.clone();
+        let mut default_name = self.default_name.clone();
+        loop {
+            let res = backend.save_file(&DialogOptions {
+                filters: &self.filters,
+                default_filter,
+                default_path: default_path.as_deref(),
+                default_name: default_name.as_deref(),
+                confirm_overwrite: self.overwrite_policy == OverwritePolicy::Confirm,
+                parent_window: self.parent_window,
+            });
+            let selection = match res {
+                DialogResult::Ok(selection) => selection,
+                res => return res,
+            };
+            let selection = if self.append_extension {
+                let filter = self
+                    .filters
+                    .get(selection.filter_index.unwrap_or(default_filter));
+                match append_extension(&self.filters, filter, &selection.value) {
+                    // The backend only confirmed the name without the extension,
+                    // so show the dialog again with the full name for the user to confirm
+                    Some(appended)
+                        if appended.exists()
+                            && self.overwrite_policy == OverwritePolicy::Confirm =>
+                    {
+                        default_path = appended.parent().map(Path::to_path_buf);
+                        default_name = appended.file_name().map(PathBuf::from);
+                        continue;
+                    }
+                    // The name now matches the filter whose extension was appended
+                    Some(appended) => Selection {
+                        value: path::owned_path(appended),
+                        filter_index: Some(selection.filter_index.unwrap_or(default_filter)),
+                    },
+                    None => selection,
+                }
+            } else {
+                selection
+            };
+            return check_save_path(
+                self.overwrite_policy,
+                self.missing_parent_policy,
+                validate_single(
+                    self.validate_selection,
+                    &self.filters,
+                    DialogResult::Ok(selection),
+                ),
+            );
+        }
+    }
+}
+impl OpenFileMultipleDialogBuilder {
+    /// Like `show()`, but also returns which filter was selected.
+    pub fn show_with_filter(&self) -> MultipleFileSelection {
+        let backend = match self.backend.get() {
+            Ok(backend) => backend,
+            Err(error) => return DialogResult::Err(error),
+        };
+        let res = backend.open_file_multiple(&DialogOptions {
+            filters: &self.filters,
+            default_filter: default_filter_index(&self.filters, self.default_filter),
+            default_path: self.default_path.as_deref(),
+            parent_window: self.parent_window,
+            ..Default::default()
+        });
+        validate_multiple(self.validate_selection, &self.filters, res)
+    }
 }
 
 impl SingleFileDialogBuilder for OpenFileDialogBuilder {
     fn show(&self) -> SingleFileResult {
-        let mut out: *mut ffi::nfdnchar_t = std::ptr::null_mut();
-        let filters = self.filters.as_ptr();
-        let filters_len = self.filters.len() as ffi::nfdfiltersize_t;
-        let default_path = self
-            .default_path
-            .as_deref()
-            .map_or_else(std::ptr::null, |p| p.as_ptr());
-        let res = unsafe { ffi::NFD_OpenDialogN(&mut out, filters, filters_len, default_path) };
-        wrap_single_file_result(res, out)
+        self.show_with_filter().map(|selection| selection.value)
+    }
+    fn show_async(self) -> DialogFuture<SingleFileResult> {
+        self.backend.clone().spawn(move || self.show(), |path| path)
     }
 }
 impl SingleFileDialogBuilder for SaveFileDialogBuilder {
     fn show(&self) -> SingleFileResult {
-        let mut out: *mut ffi::nfdnchar_t = std::ptr::null_mut();
-        let filters = self.filters.as_ptr();
-        let filters_len = self.filters.len() as ffi::nfdfiltersize_t;
-        let default_path = self
-            .default_path
-            .as_deref()
-            .map_or_else(std::ptr::null, |p| p.as_ptr());
-        let default_name = self
-            .default_name
-            .as_deref()
-            .map_or_else(std::ptr::null, |n| n.as_ptr());
-        let res = unsafe {
-            ffi::NFD_SaveDialogN(&mut out, filters, filters_len, default_path, default_name)
-        };
-        wrap_single_file_result(res, out)
+        self.show_with_filter().map(|selection| selection.value)
+    }
+    fn show_async(self) -> DialogFuture<SingleFileResult> {
+        self.backend.clone().spawn(move || self.show(), |path| path)
     }
 }
 impl SingleFileDialogBuilder for PickFolderDialogBuilder {
     fn show(&self) -> SingleFileResult {
-        let mut out: *mut ffi::nfdnchar_t = std::ptr::null_mut();
-        let default_path = self
-            .default_path
-            .as_deref()
-            .map_or_else(std::ptr::null, |p| p.as_ptr());
-        let res = unsafe { ffi::NFD_PickFolderN(&mut out, default_path) };
-        wrap_single_file_result(res, out)
+        let backend = match self.backend.get() {
+            Ok(backend) => backend,
+            Err(error) => return DialogResult::Err(error),
+        };
+        backend.pick_folder(&DialogOptions {
+            default_path: self.default_path.as_deref(),
+            parent_window: self.parent_window,
+            ..Default::default()
+        })
+    }
+    fn show_async(self) -> DialogFuture<SingleFileResult> {
+        self.backend.clone().spawn(move || self.show(), |path| path)
     }
 }
 
 impl MultipleFileDialogBuilder for OpenFileMultipleDialogBuilder {
     fn show(&self) -> MultipleFileResult {
-        let mut out: *mut ffi::nfdpathset_t = std::ptr::null_mut();
-        let filters = self.filters.as_ptr();
-        let filters_len = self.filters.len() as ffi::nfdfiltersize_t;
-        let default_path = self
-            .default_path
-            .as_deref()
-            .map_or_else(std::ptr::null, |p| p.as_ptr());
-        let res =
-            unsafe { ffi::NFD_OpenDialogMultipleN(&mut out, filters, filters_len, default_path) };
-        wrap_multiple_file_result(res, out)
+        self.show_with_filter().map(|selection| selection.value)
     }
-}
-
-impl Drop for OpenFileDialogBuilder {
-    fn drop(&mut self) {
-        destroy_filters(&mut self.filters);
+    fn show_async(self) -> DialogFuture<MultipleFileResult> {
+        self.backend.clone().spawn(
+            move || into_owned_paths(self.show()),
+            NfdPathSetBuf::from_paths,
+        )
     }
 }
-impl Drop for OpenFileMultipleDialogBuilder {
-    fn drop(&mut self) {
-        destroy_filters(&mut self.filters);
+impl MultipleFileDialogBuilder for PickFolderMultipleDialogBuilder {
+    fn show(&self) -> MultipleFileResult {
+        let backend = match self.backend.get() {
+            Ok(backend) => backend,
+            Err(error) => return DialogResult::Err(error),
+        };
+        backend.pick_folder_multiple(&DialogOptions {
+            default_path: self.default_path.as_deref(),
+            parent_window: self.parent_window,
+            ..Default::default()
+        })
     }
-}
-impl Drop for SaveFileDialogBuilder {
-    fn drop(&mut self) {
-        destroy_filters(&mut self.filters);
+    fn show_async(self) -> DialogFuture<MultipleFileResult> {
+        self.backend.clone().spawn(
+            move || into_owned_paths(self.show()),
+            NfdPathSetBuf::from_paths,
+        )
     }
 }
 
-fn make_filters<'b, 'c, I: Iterator<Item = (&'b str, &'c str)>>(
-    filters: I,
-) -> Result<Vec<ffi::nfdnfilteritem_t>, Error> {
-    filters
-        .map(|(name, spec)| make_filter(name, spec))
-        .collect::<Result<_, Error>>()
-}
-fn make_filter(name: &str, spec: &str) -> Result<ffi::nfdnfilteritem_t, Error> {
-    if spec.is_empty() {
-        return Err("Filter specification is empty");
+/// Copies the paths out of a path set, which must be done on the dialog thread,
+/// before the backend is deinitialized and while the native path set is still valid.
+fn into_owned_paths(res: MultipleFileResult) -> DialogResult<Vec<PathBuf>> {
+    match res {
+        DialogResult::Ok(pathset) => match pathset.into_paths() {
+            Ok(paths) => DialogResult::Ok(paths),
+            Err(error) => DialogResult::Err(error),
+        },
+        DialogResult::Cancel => DialogResult::Cancel,
+        DialogResult::Err(error) => DialogResult::Err(error),
     }
-    Ok(ffi::nfdnfilteritem_t {
-        name: path::str_to_native(name)?.into_raw(),
-        spec: path::str_to_native(spec)?.into_raw(),
-    })
 }
-fn destroy_filters(filters: &mut Vec<ffi::nfdnfilteritem_t>) {
-    filters
-        .drain(..)
-        .rev()
-        .for_each(|filter| destroy_filter(filter));
+
+fn make_filters<F: TryInto<Filter>, I: IntoIterator<Item = F>>(
+    filters: I,
+) -> Result<Vec<Filter>, Error>
+where
+    F::Error: Into<Error>,
+{
+    filters.into_iter().map(make_filter).collect()
 }
-fn destroy_filter(filter: ffi::nfdnfilteritem_t) {
-    let _ = unsafe { path::NfdCString::from_raw(filter.spec) };
+fn make_filter<F: TryInto<Filter>>(filter: F) -> Result<Filter, Error>
+where
+    F::Error: Into<Error>,
+{
+    filter.try_into().map_err(Into::into)
 }
 
-fn wrap_init_result(res: ffi::nfdresult_t) -> InitResult {
-    match res {
-        ffi::nfdresult_t::NFD_ERROR => Err(get_nfd_error()),
-        _ => Ok(Nfd {}),
+/// The first filter is selected if there is no filter at `index`.
+fn default_filter_index(filters: &[Filter], index: usize) -> usize {
+    if index < filters.len() {
+        index
+    } else {
+        0
     }
 }
-
-fn wrap_single_file_result(res: ffi::nfdresult_t, out: *mut ffi::nfdnchar_t) -> SingleFileResult {
+fn validate_single(
+    validate: bool,
+    filters: &[Filter],
+    res: SingleFileSelection,
+) -> SingleFileSelection {
     match res {
-        ffi::nfdresult_t::NFD_ERROR => SingleFileResult::Err(get_nfd_error()),
-        ffi::nfdresult_t::NFD_OKAY => SingleFileResult::Ok(path::wrap_path(out)),
-        ffi::nfdresult_t::NFD_CANCEL => SingleFileResult::Cancel,
+        DialogResult::Ok(selection) if validate && !matches_any(filters, &selection.value) => {
+            DialogResult::Err(Error::FilterMismatch(vec![selection
+                .value
+                .as_ref()
+                .to_path_buf()]))
+        }
+        res => res,
     }
 }
-
-fn wrap_multiple_file_result(
-    res: ffi::nfdresult_t,
-    out: *mut ffi::nfdpathset_t,
-) -> MultipleFileResult {
-    match res {
-        ffi::nfdresult_t::NFD_ERROR => MultipleFileResult::Err(get_nfd_error()),
-        ffi::nfdresult_t::NFD_OKAY => MultipleFileResult::Ok(NfdPathSetBuf::new(out)),
-        ffi::nfdresult_t::NFD_CANCEL => MultipleFileResult::Cancel,
+fn validate_multiple(
+    validate: bool,
+    filters: &[Filter],
+    res: MultipleFileSelection,
+) -> MultipleFileSelection {
+    let selection = match res {
+        DialogResult::Ok(selection) if validate && !filters.is_empty() => selection,
+        res => return res,
+    };
+    let mut mismatched = Vec::new();
+    for path in &selection.value {
+        match path {
+            Ok(path) if !matches_any(filters, &path) => {
+                mismatched.push(path.as_ref().to_path_buf())
+            }
+            Ok(_) => {}
+            Err(error) => return DialogResult::Err(error),
+        }
+    }
+    if mismatched.is_empty() {
+        DialogResult::Ok(selection)
+    } else {
+        DialogResult::Err(Error::FilterMismatch(mismatched))
+    }
+}
+/// Returns the path with the first extension of `filter` appended, if it matches none of the filters.
+fn append_extension(
+    filters: &[Filter],
+    filter: Option<&Filter>,
+    path: &NfdPathBuf,
+) -> Option<PathBuf> {
+    match filter {
+        Some(filter) if !matches_any(filters, path) => {
+            let mut appended = path.as_ref().as_os_str().to_os_string();
+            appended.push(".");
+            appended.push(&filter.extensions()[0]);
+            Some(appended.into())
+        }
+        _ => None,
+    }
+}
+fn check_save_path(
+    overwrite_policy: OverwritePolicy,
+    missing_parent_policy: MissingParentPolicy,
+    res: SingleFileSelection,
+) -> SingleFileSelection {
+    let selection = match res {
+        DialogResult::Ok(selection) => selection,
+        res => return res,
+    };
+    let chosen: &Path = selection.value.as_ref();
+    if overwrite_policy == OverwritePolicy::Forbid && chosen.exists() {
+        return DialogResult::Err(Error::FileExists(chosen.to_path_buf()));
+    }
+    match chosen.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() && !parent.is_dir() => {
+            match missing_parent_policy {
+                MissingParentPolicy::Allow => {}
+                MissingParentPolicy::Forbid => {
+                    return DialogResult::Err(Error::MissingParent(parent.to_path_buf()))
+                }
+                MissingParentPolicy::Create => {
+                    if let Err(error) = std::fs::create_dir_all(parent) {
+                        return DialogResult::Err(Error::CreateParent(
+                            parent.to_path_buf(),
+                            error.to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        _ => {}
     }
+    DialogResult::Ok(selection)
+}
+fn matches_any<P: AsRef<Path>>(filters: &[Filter], path: P) -> bool {
+    filters.is_empty() || filters.iter().any(|filter| filter.matches(path.as_ref()))
 }
 
 impl NfdPathSetBuf {
-    fn new(ptr: *mut ffi::nfdpathset_t) -> Self {
-        Self { ptr }
+    /// Takes ownership of `ptr`, which is freed even if the number of paths cannot be queried.
+    #[cfg(nfd_native)]
+    pub(crate) fn new(ptr: *mut ffi::nfdpathset_t) -> Result<Self, Error> {
+        let mut set = Self {
+            set: PathSetRepr::Native { ptr, len: 0 },
+        };
+        let mut count: ffi::nfdpathsetsize_t = 0;
+        match unsafe { ffi::NFD_PathSet_GetCount(ptr, &mut count) } {
+            ffi::nfdresult_t::NFD_OKAY => {
+                set.set = PathSetRepr::Native {
+                    ptr,
+                    len: count as usize,
+                };
+                Ok(set)
+            }
+            ffi::nfdresult_t::NFD_ERROR => Err(Error::PathSetEnum(get_nfd_error())),
+            ffi::nfdresult_t::NFD_CANCEL => Err(Error::PathSetEnum(String::from(
+                "NFD_PathSet_GetCount returned an unexpected result",
+            ))),
+        }
+    }
+    pub(crate) fn from_paths(paths: Vec<PathBuf>) -> Self {
+        Self {
+            set: PathSetRepr::Owned(paths),
+        }
+    }
+    /// Returns the number of paths in the set.
+    pub fn len(&self) -> usize {
+        match &self.set {
+            #[cfg(nfd_native)]
+            PathSetRepr::Native { len, .. } => *len,
+            PathSetRepr::Owned(paths) => paths.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Returns the path at `index`, or `None` if `index` is out of bounds.
+    /// Only native path sets can fail to retrieve a path.
+    pub fn get(&self, index: usize) -> Option<Result<NfdPathSetPathBuf, Error>> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(match &self.set {
+            #[cfg(nfd_native)]
+            PathSetRepr::Native { ptr, .. } => get_native_path(*ptr, index),
+            PathSetRepr::Owned(paths) => Ok(path::owned_pathset_path(paths[index].clone())),
+        })
     }
     pub fn iter(&self) -> path_set::Iter<'_> {
         self.into_iter()
     }
+    /// Converts the set into owned paths, failing if any of them cannot be retrieved.
+    pub fn into_paths(mut self) -> Result<Vec<PathBuf>, Error> {
+        match &mut self.set {
+            #[cfg(nfd_native)]
+            PathSetRepr::Native { .. } => {
+                self.into_iter().map(|path| path.map(Into::into)).collect()
+            }
+            PathSetRepr::Owned(paths) => Ok(std::mem::take(paths)),
+        }
+    }
 }
+/// `index` must be less than the number of paths in the set.
+#[cfg(nfd_native)]
+fn get_native_path(ptr: *mut ffi::nfdpathset_t, index: usize) -> Result<NfdPathSetPathBuf, Error> {
+    let mut out: *mut ffi::nfdnchar_t = std::ptr::null_mut();
+    let res = unsafe { ffi::NFD_PathSet_GetPathN(ptr, index as ffi::nfdpathsetsize_t, &mut out) };
+    // note: we cannot use a normal NfdPathBuf because the freeing mechanism may be different
+    match res {
+        ffi::nfdresult_t::NFD_OKAY => Ok(path::wrap_pathset_path(out)),
+        ffi::nfdresult_t::NFD_ERROR => Err(Error::PathSetEnum(get_nfd_error())),
+        ffi::nfdresult_t::NFD_CANCEL => Err(Error::PathSetEnum(String::from(
+            "NFD_PathSet_GetPathN returned an unexpected result",
+        ))),
+    }
+}
+// A native path set is not `Send`: on Windows it wraps a COM object of the thread that showed the dialog.
+// `show_async()` copies the paths on that thread instead.
+#[cfg(nfd_native)]
 impl Drop for NfdPathSetBuf {
     fn drop(&mut self) {
-        unsafe { ffi::NFD_PathSet_Free(self.ptr) };
+        if let PathSetRepr::Native { ptr, .. } = self.set {
+            unsafe { ffi::NFD_PathSet_Free(ptr) };
+        }
+    }
+}
+
+// Path sets are compared, cloned and printed by their paths, so a native set equals an owned one with the same paths
+impl Clone for NfdPathSetBuf {
+    /// The clone owns a copy of the paths.
+    ///
+    /// Panics if a path of a native set cannot be retrieved, which NFDe does not do for the sets that it returns.
+    fn clone(&self) -> Self {
+        match self.iter().map(|path| path.map(Into::into)).collect() {
+            Ok(paths) => Self::from_paths(paths),
+            Err(error) => panic!("Failed to copy path set: {}", error),
+        }
+    }
+}
+impl std::fmt::Debug for NfdPathSetBuf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut list = f.debug_list();
+        for path in self {
+            match path {
+                Ok(path) => list.entry(&path),
+                Err(error) => list.entry(&error),
+            };
+        }
+        list.finish()
+    }
+}
+impl PartialEq for NfdPathSetBuf {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+impl Eq for NfdPathSetBuf {}
+
+/// Serialized as a sequence of paths.  Fails if any of the paths cannot be retrieved.
+#[cfg(feature = "serde")]
+impl serde::Serialize for NfdPathSetBuf {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeSeq;
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for path in self {
+            seq.serialize_element(&path.map_err(serde::ser::Error::custom)?)?;
+        }
+        seq.end()
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for NfdPathSetBuf {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <Vec<PathBuf> as serde::Deserialize>::deserialize(deserializer).map(Self::from_paths)
     }
 }
 
@@ -347,56 +1205,139 @@ impl<'a> IntoIterator for &'a NfdPathSetBuf {
 }
 
 pub mod path_set {
+    #[cfg(nfd_native)]
     use super::ffi;
-    use super::get_nfd_error;
+    #[cfg(nfd_native)]
     use super::path;
     use super::Error;
     use super::NfdPathSetBuf;
     use super::NfdPathSetPathBuf;
-    use std::marker::PhantomData;
+    #[cfg(nfd_native)]
+    use super::PathSetRepr;
+    use std::ops::Range;
+
     pub struct Iter<'a> {
-        pub(super) enumerator: ffi::nfdpathsetenum_t,
-        phantom: PhantomData<&'a NfdPathSetBuf>,
+        cursor: Cursor,
+        set: &'a NfdPathSetBuf,
     }
     pub struct IntoIter {
-        pub(super) _buf: NfdPathSetBuf, // we just keep it here so that we can drop it later
-        pub(super) iter: Iter<'static>, // we don't need the lifetime enforcement here, so we set it to 'static
+        cursor: Cursor, // declared first, so that the enumerator is freed before the path set
+        set: NfdPathSetBuf,
     }
 
-    impl<'a> Iter<'a> {
-        pub(super) fn new(pathset: &'a NfdPathSetBuf) -> Self {
+    /// How far an iteration over a path set has got, independently of whether the set is borrowed or owned.
+    struct Cursor {
+        // Indices of the paths that have not been yielded yet
+        remaining: Range<usize>,
+        // Reads the front of a native path set in order, since `NFD_PathSet_GetPathN` walks a list on Linux.
+        // `None` for owned path sets, or once the enumerator has failed; paths are then read by index.
+        #[cfg(nfd_native)]
+        enumerator: Option<NativeEnum>,
+    }
+
+    /// An NFDe path set enumerator, which is freed when dropped.
+    #[cfg(nfd_native)]
+    struct NativeEnum(ffi::nfdpathsetenum_t);
+
+    #[cfg(all(nfd_native, not(target_os = "linux")))]
+    impl Drop for NativeEnum {
+        fn drop(&mut self) {
+            unsafe { ffi::NFD_PathSet_FreeEnum(&mut self.0) };
+        }
+    }
+
+    #[cfg(nfd_native)]
+    impl NativeEnum {
+        fn new(ptr: *mut ffi::nfdpathset_t) -> Option<Self> {
             let mut enumerator = ffi::nfdpathsetenum_t {
                 ptr: std::ptr::null_mut(),
             };
-            let res = unsafe { ffi::NFD_PathSet_GetEnum(pathset.ptr, &mut enumerator) };
+            match unsafe { ffi::NFD_PathSet_GetEnum(ptr, &mut enumerator) } {
+                ffi::nfdresult_t::NFD_OKAY => Some(NativeEnum(enumerator)),
+                _ => {
+                    unsafe { ffi::NFD_ClearError() };
+                    None
+                }
+            }
+        }
+
+        /// Returns `None` if the enumerator failed or ended early, after which it must not be used again.
+        fn next(&mut self) -> Option<NfdPathSetPathBuf> {
+            let mut out: *mut ffi::nfdnchar_t = std::ptr::null_mut();
+            match unsafe { ffi::NFD_PathSet_EnumNextN(&mut self.0, &mut out) } {
+                ffi::nfdresult_t::NFD_OKAY if !out.is_null() => Some(path::wrap_pathset_path(out)),
+                _ => {
+                    unsafe { ffi::NFD_ClearError() };
+                    None
+                }
+            }
+        }
+    }
+
+    // Every index yields exactly one item, so the length of the iterator is always that of the remaining range
+    impl Cursor {
+        fn new(set: &NfdPathSetBuf) -> Self {
             Self {
-                enumerator: match res {
-                    ffi::nfdresult_t::NFD_ERROR => ffi::nfdpathsetenum_t {
-                        ptr: std::ptr::null_mut(),
-                    },
-                    ffi::nfdresult_t::NFD_OKAY => enumerator,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
+                remaining: 0..set.len(),
+                #[cfg(nfd_native)]
+                enumerator: match set.set {
+                    PathSetRepr::Native { ptr, .. } => NativeEnum::new(ptr),
+                    PathSetRepr::Owned(_) => None,
                 },
-                phantom: PhantomData,
             }
         }
+
+        /// `set` must be the path set that this cursor was created for.
+        fn next(&mut self, set: &NfdPathSetBuf) -> Option<Result<NfdPathSetPathBuf, Error>> {
+            let index = self.remaining.next()?;
+            #[cfg(nfd_native)]
+            {
+                if let Some(path) = self.enumerator.as_mut().and_then(NativeEnum::next) {
+                    return Some(Ok(path));
+                }
+                // The enumerator no longer knows where it is, so read the rest by index
+                self.enumerator = None;
+            }
+            set.get(index)
+        }
+
+        fn nth(
+            &mut self,
+            n: usize,
+            set: &NfdPathSetBuf,
+        ) -> Option<Result<NfdPathSetPathBuf, Error>> {
+            #[cfg(nfd_native)]
+            {
+                if self.enumerator.is_some() {
+                    // The enumerator can only step, so the skipped paths are read and dropped
+                    for _ in 0..n {
+                        let _skipped = self.next(set)?;
+                    }
+                    return self.next(set);
+                }
+            }
+            set.get(self.remaining.nth(n)?)
+        }
+
+        fn next_back(&mut self, set: &NfdPathSetBuf) -> Option<Result<NfdPathSetPathBuf, Error>> {
+            set.get(self.remaining.next_back()?)
+        }
     }
 
-    impl IntoIter {
-        pub(super) fn new(pathset: NfdPathSetBuf) -> Self {
-            let iter = Iter::new(unsafe { std::mem::transmute(&pathset) }); // need to extend the lifetime
+    impl<'a> Iter<'a> {
+        pub(super) fn new(set: &'a NfdPathSetBuf) -> Self {
             Self {
-                _buf: pathset,
-                iter,
+                cursor: Cursor::new(set),
+                set,
             }
         }
     }
 
-    impl<'a> Drop for Iter<'a> {
-        fn drop(&mut self) {
-            #[cfg(not(target_os = "linux"))]
-            if !self.enumerator.ptr.is_null() {
-                unsafe { ffi::NFD_PathSet_FreeEnum(&mut self.enumerator) };
+    impl IntoIter {
+        pub(super) fn new(set: NfdPathSetBuf) -> Self {
+            Self {
+                cursor: Cursor::new(&set),
+                set,
             }
         }
     }
@@ -404,62 +1345,206 @@ pub mod path_set {
     impl<'a> Iterator for Iter<'a> {
         type Item = Result<NfdPathSetPathBuf, Error>;
         fn next(&mut self) -> Option<Self::Item> {
-            if self.enumerator.ptr.is_null() {
-                None
-            } else {
-                let mut out: *mut ffi::nfdnchar_t = std::ptr::null_mut();
-                let res = unsafe { ffi::NFD_PathSet_EnumNextN(&mut self.enumerator, &mut out) };
-                wrap_pathset_file_result(&mut self.enumerator, res, out)
-            }
+            self.cursor.next(self.set)
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.cursor.remaining.size_hint()
+        }
+        fn nth(&mut self, n: usize) -> Option<Self::Item> {
+            self.cursor.nth(n, self.set)
         }
     }
 
     impl Iterator for IntoIter {
         type Item = Result<NfdPathSetPathBuf, Error>;
         fn next(&mut self) -> Option<Self::Item> {
-            self.iter.next()
+            self.cursor.next(&self.set)
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.cursor.remaining.size_hint()
+        }
+        fn nth(&mut self, n: usize) -> Option<Self::Item> {
+            self.cursor.nth(n, &self.set)
+        }
+    }
+
+    impl<'a> DoubleEndedIterator for Iter<'a> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            self.cursor.next_back(self.set)
+        }
+    }
+
+    impl DoubleEndedIterator for IntoIter {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            self.cursor.next_back(&self.set)
         }
     }
 
+    impl<'a> ExactSizeIterator for Iter<'a> {}
+    impl ExactSizeIterator for IntoIter {}
+
     impl<'a> std::iter::FusedIterator for Iter<'a> {}
     impl std::iter::FusedIterator for IntoIter {}
 
-    unsafe impl<'a> Send for Iter<'a> {}
-    unsafe impl Send for IntoIter {}
-
-    fn wrap_pathset_file_result(
-        enumerator: &mut ffi::nfdpathsetenum_t,
-        res: ffi::nfdresult_t,
-        out: *mut ffi::nfdnchar_t,
-    ) -> Option<Result<NfdPathSetPathBuf, Error>> {
-        // note: we cannot use a normal NfdPathBuf because the freeing mechanism may be different
-        match res {
-            ffi::nfdresult_t::NFD_ERROR => Some(Err(get_nfd_error())),
-            ffi::nfdresult_t::NFD_OKAY => {
-                if !out.is_null() {
-                    Some(Ok(path::wrap_pathset_path(out)))
-                } else {
-                    free_enum(enumerator);
+    // Native path sets from the stand-in for NFDe, which Miri checks for use-after-free and leaks
+    // (`cargo miri test --lib`)
+    #[cfg(all(test, nfd_fake_ffi))]
+    mod tests {
+        use super::ffi;
+        use super::NfdPathSetBuf;
+        use super::NfdPathSetPathBuf;
+        use crate::path;
+        use std::path::Path;
+        use std::path::PathBuf;
 
-                    None
-                }
+        const PATHS: [&str; 3] = ["/a.txt", "/b/c.txt", "/d e.txt"];
+
+        fn path_set() -> NfdPathSetBuf {
+            NfdPathSetBuf::new(ffi::new_path_set(&PATHS)).unwrap()
+        }
+
+        fn expected() -> Vec<PathBuf> {
+            PATHS.iter().map(PathBuf::from).collect()
+        }
+
+        #[test]
+        fn iter_enumerates_every_path_in_order() {
+            let set = path_set();
+            assert_eq!(set.len(), PATHS.len());
+            let paths: Vec<PathBuf> = set.iter().map(|path| path.unwrap().into()).collect();
+            assert_eq!(paths, expected());
+            // Again, with a new enumerator
+            assert_eq!(set.iter().count(), PATHS.len());
+        }
+
+        #[test]
+        fn get_reads_by_index() {
+            let set = path_set();
+            assert_eq!(*set.get(2).unwrap().unwrap(), *Path::new(PATHS[2]));
+            assert_eq!(*set.get(0).unwrap().unwrap(), *Path::new(PATHS[0]));
+            assert!(set.get(PATHS.len()).is_none());
+        }
+
+        #[test]
+        fn next_back_after_partial_enumeration() {
+            let set = path_set();
+            let mut iter = set.iter();
+            assert_eq!(*iter.next().unwrap().unwrap(), *Path::new(PATHS[0]));
+            assert_eq!(*iter.next_back().unwrap().unwrap(), *Path::new(PATHS[2]));
+            assert_eq!(*iter.next().unwrap().unwrap(), *Path::new(PATHS[1]));
+            assert!(iter.next().is_none());
+            assert!(iter.next_back().is_none());
+        }
+
+        #[test]
+        fn nth_skips_with_the_enumerator() {
+            let mut iter = path_set().into_iter();
+            assert_eq!(*iter.nth(1).unwrap().unwrap(), *Path::new(PATHS[1]));
+            assert_eq!(*iter.next_back().unwrap().unwrap(), *Path::new(PATHS[2]));
+            assert!(iter.next().is_none());
+        }
+
+        #[test]
+        fn iterators_dropped_early() {
+            let set = path_set();
+            {
+                // The enumerator has not started yet
+                let _iter = set.iter();
             }
-            _ => unsafe { std::hint::unreachable_unchecked() },
+            {
+                let mut iter = set.iter();
+                iter.next().unwrap().unwrap();
+            }
+            assert_eq!(set.into_paths().unwrap(), expected());
+
+            let mut iter = path_set().into_iter();
+            let first = iter.next().unwrap().unwrap();
+            drop(iter);
+            assert_eq!(*first, *Path::new(PATHS[0]));
+            drop(path_set().into_iter());
+            drop(path_set());
         }
-    }
 
-    #[cfg(not(target_os = "linux"))]
-    fn free_enum(enumerator: &mut ffi::nfdpathsetenum_t) {
-        unsafe { ffi::NFD_PathSet_FreeEnum(enumerator) };
-        enumerator.ptr = std::ptr::null_mut();
-    }
+        #[test]
+        fn paths_outlive_the_set() {
+            let set = path_set();
+            let enumerated: Vec<NfdPathSetPathBuf> = set.iter().map(Result::unwrap).collect();
+            let indexed = set.get(1).unwrap().unwrap();
+            let mut iter = path_set().into_iter();
+            let from_into_iter = iter.next().unwrap().unwrap();
+            drop(iter);
+            drop(set);
+            assert_eq!(*indexed, *Path::new(PATHS[1]));
+            assert_eq!(*from_into_iter, *Path::new(PATHS[0]));
+            assert_eq!(
+                enumerated
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .collect::<Vec<_>>(),
+                expected()
+            );
+        }
+
+        #[test]
+        fn native_path_is_freed_once() {
+            let native = path::wrap_path(ffi::new_path("/a.txt"));
+            let copy = native.clone();
+            assert_eq!(native, copy);
+            assert_eq!(native.into_path_buf(), PathBuf::from("/a.txt"));
+            drop(path::wrap_path(ffi::new_path("/b.txt")));
+        }
 
-    #[cfg(target_os = "linux")]
-    fn free_enum(_enumerator: &mut ffi::nfdpathsetenum_t) {
-        // do nothing, because on Linux, the PathSet enumerator is the actual PathSet
+        #[test]
+        fn native_sets_compare_by_their_paths() {
+            let set = path_set();
+            let copy = set.clone();
+            drop(set);
+            assert_eq!(copy, NfdPathSetBuf::from_paths(expected()));
+            assert_eq!(path_set(), copy);
+            assert_ne!(
+                path_set(),
+                NfdPathSetBuf::from_paths(expected()[1..].to_vec())
+            );
+            assert_eq!(
+                format!("{:?}", path_set()),
+                r#"["/a.txt", "/b/c.txt", "/d e.txt"]"#
+            );
+        }
     }
 }
 
-fn get_nfd_error() -> Error {
-    unsafe { std::str::from_utf8_unchecked(CStr::from_ptr(ffi::NFD_GetError()).to_bytes()) }
+/// Copies the last NFDe error into owned storage and clears it.
+/// NFDe may overwrite or free its error buffer on the next call, so the message must not be borrowed.
+#[cfg(nfd_native)]
+pub(crate) fn get_nfd_error() -> String {
+    use std::ffi::CStr;
+    let ptr = unsafe { ffi::NFD_GetError() };
+    let msg = if ptr.is_null() {
+        String::from("Unknown error")
+    } else {
+        unsafe { CStr::from_ptr(ptr) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    unsafe { ffi::NFD_ClearError() };
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+
+    fn assert_traits<T: Clone + Debug + Send + Sync>() {}
+
+    // Builders and their configuration can be stored and shared by applications, e.g. in their settings
+    #[test]
+    fn builders_are_clone_debug_send_and_sync() {
+        assert_traits::<OpenFileDialogBuilder>();
+        assert_traits::<OpenFileMultipleDialogBuilder>();
+        assert_traits::<SaveFileDialogBuilder>();
+        assert_traits::<PickFolderDialogBuilder>();
+        assert_traits::<PickFolderMultipleDialogBuilder>();
+        assert_traits::<DialogConfig>();
+    }
 }
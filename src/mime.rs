@@ -0,0 +1,320 @@
+//! Expands MIME types (e.g. "image/png") into file extensions, for backends that only support extensions.
+
+#[cfg(target_os = "linux")]
+use std::sync::OnceLock;
+
+/// Common MIME types and their extensions, most common first.
+/// Used when the system has no shared-mime-info database, or the database does not know the type.
+static BUNDLED: &[(&str, &[&str])] = &[
+    ("application/epub+zip", &["epub"]),
+    ("application/gzip", &["gz"]),
+    ("application/json", &["json"]),
+    ("application/msword", &["doc"]),
+    ("application/pdf", &["pdf"]),
+    ("application/rtf", &["rtf"]),
+    ("application/vnd.ms-excel", &["xls"]),
+    ("application/vnd.ms-powerpoint", &["ppt"]),
+    ("application/vnd.oasis.opendocument.presentation", &["odp"]),
+    ("application/vnd.oasis.opendocument.spreadsheet", &["ods"]),
+    ("application/vnd.oasis.opendocument.text", &["odt"]),
+    (
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        &["pptx"],
+    ),
+    (
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        &["xlsx"],
+    ),
+    (
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        &["docx"],
+    ),
+    ("application/vnd.rar", &["rar"]),
+    ("application/wasm", &["wasm"]),
+    ("application/x-7z-compressed", &["7z"]),
+    ("application/x-tar", &["tar"]),
+    ("application/xml", &["xml"]),
+    ("application/zip", &["zip"]),
+    ("audio/aac", &["aac"]),
+    ("audio/flac", &["flac"]),
+    ("audio/mp4", &["m4a"]),
+    ("audio/mpeg", &["mp3"]),
+    ("audio/ogg", &["ogg", "oga"]),
+    ("audio/opus", &["opus"]),
+    ("audio/wav", &["wav"]),
+    ("font/otf", &["otf"]),
+    ("font/ttf", &["ttf"]),
+    ("font/woff", &["woff"]),
+    ("font/woff2", &["woff2"]),
+    ("image/avif", &["avif"]),
+    ("image/bmp", &["bmp"]),
+    ("image/gif", &["gif"]),
+    ("image/heic", &["heic"]),
+    ("image/jpeg", &["jpg", "jpeg", "jpe"]),
+    ("image/png", &["png"]),
+    ("image/svg+xml", &["svg", "svgz"]),
+    ("image/tiff", &["tif", "tiff"]),
+    ("image/vnd.microsoft.icon", &["ico"]),
+    ("image/webp", &["webp"]),
+    ("text/css", &["css"]),
+    ("text/csv", &["csv"]),
+    ("text/html", &["html", "htm"]),
+    ("text/javascript", &["js", "mjs"]),
+    ("text/markdown", &["md", "markdown"]),
+    ("text/plain", &["txt", "text"]),
+    ("video/mp4", &["mp4", "m4v"]),
+    ("video/mpeg", &["mpeg", "mpg"]),
+    ("video/quicktime", &["mov"]),
+    ("video/webm", &["webm"]),
+    ("video/x-matroska", &["mkv"]),
+    ("video/x-msvideo", &["avi"]),
+];
+
+/// Returns the extensions of a MIME type, or of every type matching a wildcard such as "image/*",
+/// in lowercase and without duplicates.  Returns an empty list if the type is unknown.
+pub(crate) fn extensions(mime_type: &str) -> Vec<String> {
+    #[cfg(target_os = "linux")]
+    let mut extensions = system_database().extensions(mime_type);
+    #[cfg(not(target_os = "linux"))]
+    let mut extensions = Vec::new();
+    push_bundled_extensions(mime_type, &mut extensions);
+    extensions
+}
+
+/// Adds the extensions of the bundled table for `mime_type` that are not in `extensions` yet.
+fn push_bundled_extensions(mime_type: &str, extensions: &mut Vec<String>) {
+    BUNDLED
+        .iter()
+        .filter(|(bundled_type, _)| matches(mime_type, bundled_type))
+        .flat_map(|(_, bundled_extensions)| bundled_extensions.iter())
+        .for_each(|extension| push_unique(extensions, extension));
+}
+
+fn push_unique(extensions: &mut Vec<String>, extension: &str) {
+    if !extensions.iter().any(|existing| existing == extension) {
+        extensions.push(extension.to_string());
+    }
+}
+
+/// Whether `mime_type` matches `pattern`, which may be a wildcard such as "image/*".
+fn matches(pattern: &str, mime_type: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(media_type) => mime_type.split('/').next() == Some(media_type),
+        None => pattern == mime_type,
+    }
+}
+
+/// The parts of the shared-mime-info database that are needed to expand MIME types.
+#[cfg(target_os = "linux")]
+#[derive(Default)]
+struct SystemDatabase {
+    /// `(mime type, extension)`, with the extensions of higher weight first.
+    globs: Vec<(String, String)>,
+    /// `(alias, canonical mime type)`
+    aliases: Vec<(String, String)>,
+}
+
+#[cfg(target_os = "linux")]
+impl SystemDatabase {
+    /// Builds the database from the contents of the `globs2` and `aliases` files of each data directory, in order.
+    /// Only globs of the form "*.ext" are used, since filters cannot express the others.
+    fn parse<S: AsRef<str>>(globs2_files: &[S], aliases_files: &[S]) -> Self {
+        let mut weighted_globs = Vec::new();
+        for globs in globs2_files {
+            // Each line is "weight:mime/type:glob", optionally followed by flags
+            for line in globs.as_ref().lines().filter(|line| !line.starts_with('#')) {
+                let mut fields = line.split(':');
+                let (weight, mime_type, glob) = match (fields.next(), fields.next(), fields.next())
+                {
+                    (Some(weight), Some(mime_type), Some(glob)) => (weight, mime_type, glob),
+                    _ => continue,
+                };
+                let extension = match glob.strip_prefix("*.") {
+                    Some(extension)
+                        if !extension.is_empty()
+                            && extension.chars().all(|c| {
+                                c.is_alphanumeric() || matches!(c, '-' | '_' | '+' | '~')
+                            }) =>
+                    {
+                        extension.to_lowercase()
+                    }
+                    _ => continue,
+                };
+                weighted_globs.push((
+                    weight.parse::<u32>().unwrap_or(50),
+                    mime_type.to_string(),
+                    extension,
+                ));
+            }
+        }
+        let mut aliases = Vec::new();
+        for aliases_file in aliases_files {
+            // Each line is "alias canonical"
+            for line in aliases_file.as_ref().lines() {
+                let mut fields = line.split_whitespace();
+                if let (Some(alias), Some(canonical)) = (fields.next(), fields.next()) {
+                    aliases.push((alias.to_string(), canonical.to_string()));
+                }
+            }
+        }
+        // The sort is stable, so globs of the same weight keep the order of the files
+        weighted_globs.sort_by_key(|&(weight, _, _)| std::cmp::Reverse(weight));
+        SystemDatabase {
+            globs: weighted_globs
+                .into_iter()
+                .map(|(_, mime_type, extension)| (mime_type, extension))
+                .collect(),
+            aliases,
+        }
+    }
+
+    /// The extensions that the database knows for `mime_type`, which may be an alias or a wildcard.
+    fn extensions(&self, mime_type: &str) -> Vec<String> {
+        let mime_type = self
+            .aliases
+            .iter()
+            .find(|(alias, _)| alias == mime_type)
+            .map_or(mime_type, |(_, canonical)| canonical.as_str());
+        let mut extensions = Vec::new();
+        self.globs
+            .iter()
+            .filter(|(glob_type, _)| matches(mime_type, glob_type))
+            .for_each(|(_, extension)| push_unique(&mut extensions, extension));
+        extensions
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn system_database() -> &'static SystemDatabase {
+    static DATABASE: OnceLock<SystemDatabase> = OnceLock::new();
+    DATABASE.get_or_init(load_system_database)
+}
+
+/// Reads the `globs2` and `aliases` files of shared-mime-info from the XDG data directories.
+#[cfg(target_os = "linux")]
+fn load_system_database() -> SystemDatabase {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".local/share"))
+        });
+    let data_dirs = std::env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    let dirs: Vec<std::path::PathBuf> = data_home
+        .into_iter()
+        .chain(std::env::split_paths(&data_dirs))
+        .collect();
+
+    let globs2_files: Vec<String> = dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_to_string(dir.join("mime/globs2")).ok())
+        .collect();
+    let aliases_files: Vec<String> = dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_to_string(dir.join("mime/aliases")).ok())
+        .collect();
+    SystemDatabase::parse(&globs2_files, &aliases_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundled_extensions(mime_type: &str) -> Vec<String> {
+        let mut extensions = Vec::new();
+        push_bundled_extensions(mime_type, &mut extensions);
+        extensions
+    }
+
+    #[test]
+    fn bundled_types_expand_to_their_extensions() {
+        assert_eq!(bundled_extensions("image/jpeg"), ["jpg", "jpeg", "jpe"]);
+        assert_eq!(bundled_extensions("text/plain"), ["txt", "text"]);
+        assert!(bundled_extensions("image/x-unknown").is_empty());
+    }
+
+    #[test]
+    fn wildcards_expand_to_every_type_of_the_media_type() {
+        let images = bundled_extensions("image/*");
+        for extension in ["png", "jpg", "jpeg", "gif", "webp", "svg"].iter() {
+            assert!(
+                images.iter().any(|image| image == extension),
+                "{}",
+                extension
+            );
+        }
+        assert!(!images.iter().any(|image| image == "txt"));
+        assert!(bundled_extensions("nothing/*").is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "reads the shared-mime-info database")]
+    fn unknown_types_have_no_extensions() {
+        assert!(extensions("application/x-nfde-unknown").is_empty());
+        assert!(extensions("x-nfde-unknown/*").is_empty());
+    }
+
+    #[test]
+    fn bundled_extensions_are_added_after_the_system_ones() {
+        let mut extensions = vec![String::from("jpeg"), String::from("jfif")];
+        push_bundled_extensions("image/jpeg", &mut extensions);
+        assert_eq!(extensions, ["jpeg", "jfif", "jpg", "jpe"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    const GLOBS2: &str = "\
+# Generated by update-mime-database
+50:text/x-log:*.log
+80:image/jpeg:*.jpg
+50:image/jpeg:*.JPEG
+50:image/png:*.png:cs
+50:text/x-makefile:Makefile
+50:text/x-backup:*~
+malformed line
+";
+
+    #[cfg(target_os = "linux")]
+    const ALIASES: &str = "\
+image/pjpeg image/jpeg
+image/x-png image/png
+";
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn globs2_files_are_parsed() {
+        let database = SystemDatabase::parse(&[GLOBS2], &[ALIASES]);
+        assert_eq!(
+            database.globs,
+            [
+                ("image/jpeg", "jpg"),
+                ("text/x-log", "log"),
+                ("image/jpeg", "jpeg"),
+                ("image/png", "png"),
+            ]
+            .iter()
+            .map(|&(mime_type, extension)| (mime_type.to_string(), extension.to_string()))
+            .collect::<Vec<_>>()
+        );
+        assert_eq!(database.aliases.len(), 2);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn globs_are_ordered_by_weight_across_files() {
+        let database = SystemDatabase::parse(&[GLOBS2, "60:image/png:*.apng\n"], &[]);
+        assert_eq!(database.extensions("image/png"), ["apng", "png"]);
+        assert_eq!(database.extensions("image/jpeg"), ["jpg", "jpeg"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn the_system_database_resolves_aliases_and_wildcards() {
+        let database = SystemDatabase::parse(&[GLOBS2], &[ALIASES]);
+        assert_eq!(database.extensions("image/pjpeg"), ["jpg", "jpeg"]);
+        assert_eq!(database.extensions("image/*"), ["jpg", "jpeg", "png"]);
+        assert_eq!(database.extensions("text/*"), ["log"]);
+        assert_eq!(database.extensions("image/x-png"), ["png"]);
+        assert!(database.extensions("image/gif").is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+//! The filters of a dialog, in the form that NFDe takes them.
+//! Only the layout of `nfdnfilteritem_t` is needed to build them, so this is compiled (and tested)
+//! even when NFDe is not linked.
+
+#![cfg_attr(not(nfd_native), allow(dead_code))]
+
+use crate::backend::DialogOptions;
+use crate::ffi;
+use crate::path;
+use crate::Error;
+
+/// The filters as native strings, which own the memory that the `nfdnfilteritem_t`s passed to NFDe point to.
+pub(crate) struct NativeFilters {
+    strings: Vec<(path::NfdCString, path::NfdCString)>,
+}
+
+impl NativeFilters {
+    /// NFDe always selects the first filter, so the default filter is moved to the front.
+    pub(crate) fn new(options: &DialogOptions<'_>) -> Result<Self, Error> {
+        let strings = options
+            .filters_default_first()
+            .into_iter()
+            .map(|(_, filter)| {
+                Ok((
+                    path::str_to_native(filter.name())?,
+                    path::str_to_native(&filter.spec())?,
+                ))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { strings })
+    }
+
+    /// The items borrow from `self`, so they must not outlive it.
+    pub(crate) fn items(&self) -> Vec<ffi::nfdnfilteritem_t> {
+        self.strings
+            .iter()
+            .map(|(name, spec)| ffi::nfdnfilteritem_t {
+                name: name.as_ptr(),
+                spec: spec.as_ptr(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_alloc::assert_no_leaks;
+    use crate::Filter;
+    use std::path::Path;
+
+    fn filters() -> Vec<Filter> {
+        vec![
+            Filter::new("Images", &["png", "jpg"]).unwrap(),
+            Filter::new("Text", &["txt"]).unwrap(),
+        ]
+    }
+
+    fn native(s: &str) -> path::NfdCString {
+        path::str_to_native(s).unwrap()
+    }
+
+    #[test]
+    fn native_filters_put_the_default_filter_first() {
+        let filters = filters();
+        let native_filters = NativeFilters::new(&DialogOptions {
+            filters: &filters,
+            default_filter: 1,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            native_filters.strings,
+            [
+                (native("Text"), native("txt")),
+                (native("Images"), native("png,jpg")),
+            ]
+        );
+    }
+
+    #[test]
+    fn native_filters_keep_the_order_if_the_default_filter_is_out_of_range() {
+        let filters = filters();
+        let native_filters = NativeFilters::new(&DialogOptions {
+            filters: &filters,
+            default_filter: 2,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            native_filters.strings,
+            [
+                (native("Images"), native("png,jpg")),
+                (native("Text"), native("txt")),
+            ]
+        );
+    }
+
+    #[test]
+    fn native_filter_items_point_into_the_strings() {
+        let filters = filters();
+        let native_filters = NativeFilters::new(&DialogOptions {
+            filters: &filters,
+            ..Default::default()
+        })
+        .unwrap();
+        let items = native_filters.items();
+        assert_eq!(items.len(), 2);
+        for (item, (name, spec)) in items.iter().zip(&native_filters.strings) {
+            assert_eq!(item.name, name.as_ptr());
+            assert_eq!(item.spec, spec.as_ptr());
+        }
+    }
+
+    #[test]
+    fn native_filters_without_filters() {
+        let native_filters = NativeFilters::new(&DialogOptions::default()).unwrap();
+        assert!(native_filters.items().is_empty());
+    }
+
+    // Builds the filters and items like the native backend does for a dialog with these options
+    fn build(options: &DialogOptions<'_>) {
+        let native_filters = NativeFilters::new(options).unwrap();
+        let items = native_filters.items();
+        assert_eq!(items.len(), options.filters.len());
+    }
+
+    #[test]
+    fn native_filters_of_an_open_dialog_do_not_leak() {
+        let filters = filters();
+        assert_no_leaks(|| {
+            build(&DialogOptions {
+                filters: &filters,
+                default_filter: 1,
+                default_path: Some(Path::new("/home/user")),
+                ..Default::default()
+            })
+        });
+    }
+
+    #[test]
+    fn native_filters_of_a_save_dialog_do_not_leak() {
+        let filters = filters();
+        assert_no_leaks(|| {
+            build(&DialogOptions {
+                filters: &filters,
+                default_path: Some(Path::new("/home/user")),
+                default_name: Some(Path::new("a.png")),
+                confirm_overwrite: true,
+                ..Default::default()
+            })
+        });
+    }
+
+    #[test]
+    fn native_filters_of_a_multiple_open_dialog_do_not_leak() {
+        let filters = filters();
+        assert_no_leaks(|| {
+            build(&DialogOptions {
+                filters: &filters[1..],
+                ..Default::default()
+            });
+            build(&DialogOptions::default());
+        });
+    }
+}
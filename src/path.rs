@@ -1,154 +1,281 @@
+#[cfg(nfd_native)]
 use crate::ffi;
 use crate::Error;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ops::Deref;
 use std::path::Path;
+use std::path::PathBuf;
 use std::result::Result;
 
-const C_STRING_INTERIOR_NULL_ERROR_MESSAGE: &'static str = "Path has interior null values";
-
 #[cfg(target_os = "windows")]
 mod pathutil {
     use crate::ffi;
     //use std::ffi::OsString;
     //use std::os::windows::ffi::OsStrExt;
     //use std::os::windows::ffi::OsStringExt;
+    use super::NfdPathBuf;
+    use super::NfdPathSetPathBuf;
+    use super::PathRepr;
     use std::path::Path;
-    use std::path::PathBuf;
     use widestring::U16CStr;
     use widestring::U16CString;
 
     pub type NfdCStr = U16CStr;
     pub type NfdCString = U16CString;
-    pub type NfdPathBuf = PathBuf;
-    pub type NfdPathSetPathBuf = PathBuf;
 
+    // Windows paths have to be converted anyway, so they are copied into Rust-owned memory straight away
     pub fn wrap_path(path: *mut ffi::nfdnchar_t) -> NfdPathBuf {
         //let ret = OsString::from_wide(unsafe { NfdCStr::from_ptr_str(path) }.as_slice());
         let ret = unsafe { NfdCStr::from_ptr_str(path) }.to_os_string();
         unsafe { ffi::NFD_FreePathN(path) };
-        ret.into()
+        NfdPathBuf {
+            path: PathRepr::Owned(ret.into()),
+        }
     }
 
     pub fn wrap_pathset_path(path: *mut ffi::nfdnchar_t) -> NfdPathSetPathBuf {
         //let ret = OsString::from_wide(unsafe { NfdCStr::from_ptr_str(path) }.as_slice());
         let ret = unsafe { NfdCStr::from_ptr_str(path) }.to_os_string();
         unsafe { ffi::NFD_PathSet_FreePathN(path) };
-        ret.into()
+        NfdPathSetPathBuf {
+            path: PathRepr::Owned(ret.into()),
+        }
     }
 
     pub fn unwrap_path(path: &Path) -> Result<NfdCString, super::Error> {
-        NfdCString::from_os_str(path.as_os_str())
-            .map_err(|_| super::C_STRING_INTERIOR_NULL_ERROR_MESSAGE)
+        NfdCString::from_os_str(path.as_os_str()).map_err(|_| super::Error::InteriorNul)
     }
 
     pub fn str_to_native(s: &str) -> Result<NfdCString, super::Error> {
-        NfdCString::from_str(s).map_err(|_| super::C_STRING_INTERIOR_NULL_ERROR_MESSAGE)
+        NfdCString::from_str(s).map_err(|_| super::Error::InteriorNul)
     }
 }
 
 #[cfg(not(target_os = "windows"))]
 mod pathutil {
+    #[cfg(nfd_native)]
     use crate::ffi;
+    #[cfg(nfd_native)]
     use std::ffi::CStr;
     use std::ffi::CString;
+    #[cfg(nfd_native)]
     use std::ffi::OsStr;
-    use std::ops::Deref;
     //use std::os::unix::ffi::OsString;
-    use std::borrow::Borrow;
+    #[cfg(nfd_native)]
+    use super::NfdPathBuf;
+    #[cfg(nfd_native)]
+    use super::NfdPathSetPathBuf;
+    #[cfg(nfd_native)]
+    use super::PathRepr;
     use std::os::unix::ffi::OsStrExt;
     use std::path::Path;
 
+    #[cfg(nfd_native)]
     pub type NfdCStr = CStr;
     pub type NfdCString = CString;
-    pub struct NfdPathBuf {
-        path: *mut ffi::nfdnchar_t,
-    }
-    pub struct NfdPathSetPathBuf {
-        path: *mut ffi::nfdnchar_t,
-    }
 
-    impl Drop for NfdPathBuf {
-        fn drop(&mut self) {
-            unsafe { ffi::NFD_FreePathN(self.path) };
-        }
-    }
-    impl Drop for NfdPathSetPathBuf {
-        fn drop(&mut self) {
-            unsafe { ffi::NFD_PathSet_FreePathN(self.path) };
-        }
+    /// `path` must be a valid NFDe path.
+    #[cfg(nfd_native)]
+    pub fn native_as_path<'a>(path: *mut ffi::nfdnchar_t) -> &'a Path {
+        OsStr::from_bytes(unsafe { NfdCStr::from_ptr(path) }.to_bytes()).as_ref()
     }
-    impl NfdPathBuf {
-        pub fn as_path(&self) -> &Path {
-            OsStr::from_bytes(unsafe { NfdCStr::from_ptr(self.path) }.to_bytes()).as_ref()
-        }
-    }
-    impl NfdPathSetPathBuf {
-        pub fn as_path(&self) -> &Path {
-            OsStr::from_bytes(unsafe { NfdCStr::from_ptr(self.path) }.to_bytes()).as_ref()
-        }
-    }
-    impl Deref for NfdPathBuf {
-        type Target = Path;
-        fn deref(&self) -> &Self::Target {
-            self.as_path()
+
+    #[cfg(nfd_native)]
+    pub fn wrap_path(path: *mut ffi::nfdnchar_t) -> NfdPathBuf {
+        NfdPathBuf {
+            path: PathRepr::Native(path),
         }
     }
-    impl Deref for NfdPathSetPathBuf {
-        type Target = Path;
-        fn deref(&self) -> &Self::Target {
-            self.as_path()
+
+    // With GTK, the path points into the path set and is freed with it, so it is copied straight away.
+    // Elsewhere it is a separate allocation, but copying it too keeps every path independent of its set.
+    #[cfg(nfd_native)]
+    pub fn wrap_pathset_path(path: *mut ffi::nfdnchar_t) -> NfdPathSetPathBuf {
+        let ret = native_as_path(path).to_path_buf();
+        unsafe { ffi::NFD_PathSet_FreePathN(path) };
+        NfdPathSetPathBuf {
+            path: PathRepr::Owned(ret),
         }
     }
-    impl Borrow<Path> for NfdPathBuf {
-        fn borrow(&self) -> &Path {
-            self.as_path()
-        }
+
+    pub fn unwrap_path(path: &Path) -> Result<NfdCString, super::Error> {
+        NfdCString::new(path.as_os_str().as_bytes()).map_err(|_| super::Error::InteriorNul)
     }
-    impl Borrow<Path> for NfdPathSetPathBuf {
-        fn borrow(&self) -> &Path {
-            self.as_path()
-        }
+
+    pub fn str_to_native(s: &str) -> Result<NfdCString, super::Error> {
+        NfdCString::new(s).map_err(|_| super::Error::InteriorNul)
     }
-    impl AsRef<Path> for NfdPathBuf {
-        fn as_ref(&self) -> &Path {
-            self.as_path()
+}
+
+pub use pathutil::NfdCString;
+
+// A path is either allocated by NFDe, or owned by Rust (e.g. when it comes from a non-native backend, or on Windows)
+enum PathRepr {
+    #[cfg(all(nfd_native, not(target_os = "windows")))]
+    Native(*mut ffi::nfdnchar_t),
+    Owned(PathBuf),
+}
+impl PathRepr {
+    fn as_path(&self) -> &Path {
+        match self {
+            #[cfg(all(nfd_native, not(target_os = "windows")))]
+            PathRepr::Native(path) => pathutil::native_as_path(*path),
+            PathRepr::Owned(path) => path.as_path(),
         }
     }
-    impl AsRef<Path> for NfdPathSetPathBuf {
-        fn as_ref(&self) -> &Path {
-            self.as_path()
+    fn take_path_buf(&mut self) -> PathBuf {
+        match self {
+            // The native path is freed when its owner is dropped
+            #[cfg(all(nfd_native, not(target_os = "windows")))]
+            PathRepr::Native(path) => pathutil::native_as_path(*path).to_path_buf(),
+            PathRepr::Owned(path) => std::mem::take(path),
         }
     }
+}
 
-    pub fn wrap_path(path: *mut ffi::nfdnchar_t) -> NfdPathBuf {
-        NfdPathBuf { path }
-    }
-
-    pub fn wrap_pathset_path(path: *mut ffi::nfdnchar_t) -> NfdPathSetPathBuf {
-        NfdPathSetPathBuf { path }
-    }
+pub struct NfdPathBuf {
+    path: PathRepr,
+}
+pub struct NfdPathSetPathBuf {
+    path: PathRepr,
+}
 
-    pub fn unwrap_path(path: &Path) -> Result<NfdCString, super::Error> {
-        NfdCString::new(path.as_os_str().as_bytes())
-            .map_err(|_| super::C_STRING_INTERIOR_NULL_ERROR_MESSAGE)
-    }
+// A native path is owned by the `NfdPathBuf`, is never modified, and NFDe allows it to be freed from any thread.
+// A `NfdPathSetPathBuf` never holds a native path, since set paths are copied out of their set by `wrap_pathset_path`.
+unsafe impl Send for NfdPathBuf {}
+unsafe impl Send for NfdPathSetPathBuf {}
+unsafe impl Sync for NfdPathBuf {}
+unsafe impl Sync for NfdPathSetPathBuf {}
 
-    pub fn str_to_native(s: &str) -> Result<NfdCString, super::Error> {
-        NfdCString::new(s).map_err(|_| super::C_STRING_INTERIOR_NULL_ERROR_MESSAGE)
+#[cfg(all(nfd_native, not(target_os = "windows")))]
+impl Drop for NfdPathBuf {
+    fn drop(&mut self) {
+        if let PathRepr::Native(path) = self.path {
+            unsafe { ffi::NFD_FreePathN(path) };
+        }
     }
 }
 
-pub use pathutil::NfdCString;
-pub use pathutil::NfdPathBuf;
-pub use pathutil::NfdPathSetPathBuf;
+// Both path types behave like an immutable `PathBuf` on every platform
+macro_rules! impl_path_buf {
+    ($name:ident) => {
+        impl $name {
+            pub fn as_path(&self) -> &Path {
+                self.path.as_path()
+            }
+            /// Converts the path into a `PathBuf`, copying it if it was allocated by NFDe.
+            pub fn into_path_buf(mut self) -> PathBuf {
+                self.path.take_path_buf()
+            }
+        }
+        impl Deref for $name {
+            type Target = Path;
+            fn deref(&self) -> &Self::Target {
+                self.as_path()
+            }
+        }
+        impl Borrow<Path> for $name {
+            fn borrow(&self) -> &Path {
+                self.as_path()
+            }
+        }
+        impl AsRef<Path> for $name {
+            fn as_ref(&self) -> &Path {
+                self.as_path()
+            }
+        }
+        impl From<$name> for PathBuf {
+            fn from(path: $name) -> Self {
+                path.into_path_buf()
+            }
+        }
+        impl From<PathBuf> for $name {
+            fn from(path: PathBuf) -> Self {
+                Self {
+                    path: PathRepr::Owned(path),
+                }
+            }
+        }
+        impl Clone for $name {
+            fn clone(&self) -> Self {
+                Self::from(self.as_path().to_path_buf())
+            }
+        }
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(self.as_path(), f)
+            }
+        }
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.as_path() == other.as_path()
+            }
+        }
+        impl Eq for $name {}
+        impl PartialEq<Path> for $name {
+            fn eq(&self, other: &Path) -> bool {
+                self.as_path() == other
+            }
+        }
+        impl PartialEq<PathBuf> for $name {
+            fn eq(&self, other: &PathBuf) -> bool {
+                self.as_path() == other.as_path()
+            }
+        }
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.as_path().cmp(other.as_path())
+            }
+        }
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.as_path().hash(state)
+            }
+        }
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serde::Serialize::serialize(self.as_path(), serializer)
+            }
+        }
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <PathBuf as serde::Deserialize>::deserialize(deserializer).map(Self::from)
+            }
+        }
+    };
+}
+impl_path_buf!(NfdPathBuf);
+impl_path_buf!(NfdPathSetPathBuf);
 
+#[cfg(nfd_native)]
 pub fn wrap_path(path: *mut ffi::nfdnchar_t) -> NfdPathBuf {
     pathutil::wrap_path(path)
 }
 
+#[cfg(nfd_native)]
 pub fn wrap_pathset_path(path: *mut ffi::nfdnchar_t) -> NfdPathSetPathBuf {
     pathutil::wrap_pathset_path(path)
 }
 
+pub fn owned_path(path: PathBuf) -> NfdPathBuf {
+    path.into()
+}
+
+pub fn owned_pathset_path(path: PathBuf) -> NfdPathSetPathBuf {
+    path.into()
+}
+
 pub fn unwrap_path(path: &Path) -> Result<NfdCString, Error> {
     pathutil::unwrap_path(path)
 }
@@ -0,0 +1,57 @@
+//! A global allocator that counts live bytes per thread, to check code for leaks.
+//! Installed for the unit tests of the crate by lib.rs, and shared with tests/leaks.rs through tests/common.
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+
+/// Counts the bytes allocated by each thread.  Install it with `#[global_allocator]`.
+pub struct CountingAllocator;
+
+thread_local! {
+    // Bytes allocated and not yet freed by the current thread
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+fn count(bytes: isize) {
+    // The counter has no destructor, so this only fails while the thread is being set up
+    let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + bytes));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            count(layout.size() as isize);
+        }
+        ptr
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            count(layout.size() as isize);
+        }
+        ptr
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        count(-(layout.size() as isize));
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            count(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+/// Runs `scenario` once to warm up lazily allocated state, then again while the bytes allocated
+/// by the current thread are counted, and checks that they were all freed.
+pub fn assert_no_leaks<F: Fn()>(scenario: F) {
+    scenario();
+    let before = LIVE_BYTES.with(Cell::get);
+    scenario();
+    assert_eq!(LIVE_BYTES.with(Cell::get), before, "bytes leaked");
+}
@@ -0,0 +1,26 @@
+//! Fixtures shared by the integration tests.
+
+// Each test file uses only some of them
+#![allow(dead_code)]
+
+#[path = "../../src/test_alloc.rs"]
+pub mod alloc;
+
+use nfde::backend::mock::MockBackend;
+use nfde::Filter;
+use nfde::Nfd;
+use std::sync::Arc;
+
+/// An `Nfd` with a fresh `MockBackend`, which responses can be queued on.
+pub fn setup() -> (Arc<MockBackend>, Nfd) {
+    let mock = Arc::new(MockBackend::new());
+    let nfd = Nfd::with_backend(mock.clone()).unwrap();
+    (mock, nfd)
+}
+
+pub fn filters() -> Vec<Filter> {
+    vec![
+        Filter::new("Images", &["png", "jpg"]).unwrap(),
+        Filter::new("Text", &["txt"]).unwrap(),
+    ]
+}
@@ -0,0 +1,128 @@
+//! Checks that showing dialogs with every builder frees everything it allocates.
+//! Each scenario runs once to warm up lazily allocated state, then again while the bytes allocated
+//! by the test thread are counted.
+//! The native filter strings that NFDe is given are checked in the same way in `src/native_filters.rs`.
+
+mod common;
+
+use common::alloc::assert_no_leaks;
+use common::alloc::CountingAllocator;
+use common::filters;
+use common::setup;
+use nfde::*;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn open_file() {
+    assert_no_leaks(|| {
+        let (mock, nfd) = setup();
+        mock.push_ok_with_filter("/home/user/a.png", 0);
+        mock.push_cancel();
+        mock.push_err(Error::Dialog(String::from("scripted failure")));
+        mock.push_ok("/home/user/a.pdf");
+        let mut builder = nfd.open_file();
+        builder
+            .add_filters(filters())
+            .unwrap()
+            .default_filter(1)
+            .default_path("/home/user")
+            .unwrap()
+            .validate_selection(true);
+        assert!(builder.show_with_filter().into_result().unwrap().is_some());
+        assert_eq!(builder.show(), DialogResult::Cancel);
+        assert!(builder.show().into_result().is_err());
+        assert!(matches!(
+            builder.show(),
+            DialogResult::Err(Error::FilterMismatch(_))
+        ));
+    });
+}
+
+#[test]
+fn open_file_multiple() {
+    assert_no_leaks(|| {
+        let (mock, nfd) = setup();
+        mock.push_ok_multiple(vec!["/a.png", "/b.txt", "/c.txt"]);
+        mock.push_ok_multiple(vec!["/a.png", "/b.txt"]);
+        mock.push_cancel();
+        let mut builder = nfd.open_file_multiple();
+        builder.add_filters(filters()).unwrap();
+        let set = builder.show().into_result().unwrap().unwrap();
+        {
+            // Dropped before the end of the set
+            let mut iter = set.iter();
+            iter.next().unwrap().unwrap();
+        }
+        assert_eq!(set.into_iter().count(), 3);
+        let selection = builder.show_with_filter().into_result().unwrap().unwrap();
+        assert_eq!(selection.value.into_paths().unwrap().len(), 2);
+        assert_eq!(builder.show(), DialogResult::Cancel);
+    });
+}
+
+#[test]
+fn save_file() {
+    assert_no_leaks(|| {
+        let (mock, nfd) = setup();
+        mock.push_ok_with_filter("/nonexistent/report", 1);
+        mock.push_cancel();
+        let mut builder = nfd.save_file();
+        builder
+            .add_filters(filters())
+            .unwrap()
+            .default_path("/nonexistent")
+            .unwrap()
+            .default_name(&"report.txt")
+            .unwrap()
+            .append_extension(true);
+        let selection = builder.show_with_filter().into_result().unwrap().unwrap();
+        assert_eq!(selection.value.to_str(), Some("/nonexistent/report.txt"));
+        assert_eq!(builder.show(), DialogResult::Cancel);
+    });
+}
+
+#[test]
+fn pick_folder() {
+    assert_no_leaks(|| {
+        let (mock, nfd) = setup();
+        mock.push_ok("/home/user/docs");
+        mock.push_err(Error::Dialog(String::from("scripted failure")));
+        let mut builder = nfd.pick_folder();
+        builder.default_path("/home/user").unwrap();
+        assert!(builder.show().into_result().unwrap().is_some());
+        assert!(builder.show().into_result().is_err());
+    });
+}
+
+#[test]
+fn pick_folder_multiple() {
+    assert_no_leaks(|| {
+        let (mock, nfd) = setup();
+        mock.push_ok_multiple(vec!["/a", "/b"]);
+        mock.push_ok_multiple(vec!["/a", "/b"]);
+        let builder = nfd.pick_folder_multiple();
+        let set = builder.show().into_result().unwrap().unwrap();
+        assert_eq!(set.iter().rev().count(), 2);
+        drop(set);
+        // Dropped without being iterated
+        builder.show().into_result().unwrap().unwrap();
+    });
+}
+
+#[test]
+fn config() {
+    assert_no_leaks(|| {
+        let mut builder = OpenFileDialogBuilder::new();
+        builder
+            .add_filters(filters())
+            .unwrap()
+            .default_path("/home/user")
+            .unwrap();
+        let config = builder.config();
+        let mut save = SaveFileDialogBuilder::new();
+        save.apply_config(&config).unwrap();
+        assert_eq!(save.config().filters, config.filters);
+    });
+}
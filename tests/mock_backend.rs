@@ -0,0 +1,512 @@
+mod common;
+
+use common::filters;
+use common::setup;
+use nfde::backend::mock::MockCall;
+use nfde::backend::mock::MockResponse;
+use nfde::backend::DialogKind;
+use nfde::*;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+fn paths(pathset: NfdPathSetBuf) -> Vec<PathBuf> {
+    pathset.into_paths().unwrap()
+}
+
+/// A folder with an existing "a.txt" for the save policies to check against, which is removed when dropped.
+struct TestDir(PathBuf);
+
+impl TestDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("nfde-mock-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "").unwrap();
+        TestDir(dir)
+    }
+
+    fn join(&self, path: &str) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn open_file_records_options() {
+    let (mock, nfd) = setup();
+    mock.push_ok("/home/user/a.png");
+    let res = nfd
+        .open_file()
+        .add_filters(filters())
+        .unwrap()
+        .default_path("/home/user")
+        .unwrap()
+        .show();
+    assert_eq!(
+        res.into_result().unwrap().unwrap(),
+        *Path::new("/home/user/a.png")
+    );
+    assert_eq!(
+        mock.calls(),
+        [MockCall {
+            kind: DialogKind::OpenFile,
+            filters: filters(),
+            default_filter: 0,
+            default_path: Some(PathBuf::from("/home/user")),
+            default_name: None,
+            confirm_overwrite: false,
+        }]
+    );
+}
+
+#[test]
+fn open_file_multiple_records_options() {
+    let (mock, nfd) = setup();
+    mock.push_ok_multiple(vec!["/a.png", "/b.txt"]);
+    let res = nfd
+        .open_file_multiple()
+        .add_filters(filters())
+        .unwrap()
+        .default_filter(1)
+        .default_path("/home")
+        .unwrap()
+        .show();
+    assert_eq!(
+        paths(res.into_result().unwrap().unwrap()),
+        [PathBuf::from("/a.png"), PathBuf::from("/b.txt")]
+    );
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].kind, DialogKind::OpenFileMultiple);
+    assert_eq!(calls[0].filters, filters());
+    assert_eq!(calls[0].default_filter, 1);
+    assert_eq!(calls[0].default_path, Some(PathBuf::from("/home")));
+}
+
+#[test]
+fn multiple_results_compare_by_their_paths() {
+    let (mock, nfd) = setup();
+    for _ in 0..2 {
+        mock.push_response(MockResponse::OkWithFilter(
+            vec![PathBuf::from("/a.png"), PathBuf::from("/b.png")],
+            0,
+        ));
+    }
+    let mut builder = nfd.open_file_multiple();
+    builder.add_filters(filters()).unwrap();
+    let first = builder.show_with_filter();
+    assert_eq!(first, builder.show_with_filter());
+    assert_eq!(first.clone(), first);
+    assert_eq!(
+        format!("{:?}", first),
+        r#"Ok(Selection { value: ["/a.png", "/b.png"], filter_index: Some(0) })"#
+    );
+}
+
+#[test]
+fn save_file_records_options() {
+    let (mock, nfd) = setup();
+    mock.push_ok("/home/user/out.txt");
+    let res = nfd
+        .save_file()
+        .add_filters(filters())
+        .unwrap()
+        .default_path("/home/user")
+        .unwrap()
+        .default_name(&"out.txt")
+        .unwrap()
+        .overwrite_policy(OverwritePolicy::Allow)
+        .show();
+    assert_eq!(
+        res.into_result().unwrap().unwrap(),
+        *Path::new("/home/user/out.txt")
+    );
+    assert_eq!(
+        mock.calls(),
+        [MockCall {
+            kind: DialogKind::SaveFile,
+            filters: filters(),
+            default_filter: 0,
+            default_path: Some(PathBuf::from("/home/user")),
+            default_name: Some(PathBuf::from("out.txt")),
+            confirm_overwrite: false,
+        }]
+    );
+}
+
+#[test]
+fn pick_folder_records_options() {
+    let (mock, nfd) = setup();
+    mock.push_ok("/home/user/docs");
+    let res = nfd.pick_folder().default_path("/home/user").unwrap().show();
+    assert_eq!(
+        res.into_result().unwrap().unwrap(),
+        *Path::new("/home/user/docs")
+    );
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].kind, DialogKind::PickFolder);
+    assert!(calls[0].filters.is_empty());
+    assert_eq!(calls[0].default_path, Some(PathBuf::from("/home/user")));
+    assert_eq!(calls[0].default_name, None);
+}
+
+#[test]
+fn pick_folder_multiple_records_options() {
+    let (mock, nfd) = setup();
+    mock.push_ok_multiple(vec!["/a", "/b", "/c"]);
+    let res = nfd.pick_folder_multiple().show();
+    assert_eq!(
+        paths(res.into_result().unwrap().unwrap()),
+        [
+            PathBuf::from("/a"),
+            PathBuf::from("/b"),
+            PathBuf::from("/c")
+        ]
+    );
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].kind, DialogKind::PickFolderMultiple);
+    assert_eq!(calls[0].default_path, None);
+}
+
+#[test]
+fn cancel_is_returned_by_every_builder() {
+    let (mock, nfd) = setup();
+    for _ in 0..5 {
+        mock.push_cancel();
+    }
+    assert_eq!(nfd.open_file().show(), DialogResult::Cancel);
+    assert_eq!(nfd.open_file_multiple().show(), DialogResult::Cancel);
+    assert_eq!(nfd.save_file().show(), DialogResult::Cancel);
+    assert_eq!(nfd.pick_folder().show(), DialogResult::Cancel);
+    assert_eq!(nfd.pick_folder_multiple().show(), DialogResult::Cancel);
+    assert_eq!(mock.pending_responses(), 0);
+    let kinds: Vec<_> = mock.calls().into_iter().map(|call| call.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            DialogKind::OpenFile,
+            DialogKind::OpenFileMultiple,
+            DialogKind::SaveFile,
+            DialogKind::PickFolder,
+            DialogKind::PickFolderMultiple,
+        ]
+    );
+}
+
+#[test]
+fn errors_are_returned_by_every_builder() {
+    let (mock, nfd) = setup();
+    let error = Error::Dialog(String::from("scripted failure"));
+    for _ in 0..5 {
+        mock.push_err(error.clone());
+    }
+    assert_eq!(nfd.open_file().show(), DialogResult::Err(error.clone()));
+    assert_eq!(
+        nfd.open_file_multiple().show(),
+        DialogResult::Err(error.clone())
+    );
+    assert_eq!(nfd.save_file().show(), DialogResult::Err(error.clone()));
+    assert_eq!(nfd.pick_folder().show(), DialogResult::Err(error.clone()));
+    assert_eq!(
+        nfd.pick_folder_multiple().show(),
+        DialogResult::Err(error.clone())
+    );
+}
+
+#[test]
+fn missing_response_is_an_error() {
+    let (mock, nfd) = setup();
+    assert!(matches!(
+        nfd.open_file().show(),
+        DialogResult::Err(Error::Dialog(_))
+    ));
+    assert_eq!(mock.calls().len(), 1);
+}
+
+#[test]
+fn single_dialogs_reject_multiple_paths() {
+    let (mock, nfd) = setup();
+    mock.push_ok_multiple(vec!["/a", "/b"]);
+    assert!(matches!(
+        nfd.open_file().show(),
+        DialogResult::Err(Error::Dialog(_))
+    ));
+}
+
+#[test]
+fn selected_filter_is_reported() {
+    let (mock, nfd) = setup();
+    mock.push_ok_with_filter("/a.txt", 1);
+    let res = nfd
+        .open_file()
+        .add_filters(filters())
+        .unwrap()
+        .show_with_filter();
+    let selection = res.into_result().unwrap().unwrap();
+    assert_eq!(selection.value, *Path::new("/a.txt"));
+    assert_eq!(selection.filter_index, Some(1));
+}
+
+#[test]
+fn all_supported_is_shown_before_the_filters_it_merges() {
+    let (mock, nfd) = setup();
+    mock.push_cancel();
+    let res = nfd
+        .open_file()
+        .add_filter(filters::all_supported([filters::IMAGES, filters::DOCUMENTS]).unwrap())
+        .unwrap()
+        .add_filters([filters::IMAGES, filters::DOCUMENTS])
+        .unwrap()
+        .show();
+    assert_eq!(res, DialogResult::Cancel);
+    let calls = mock.calls();
+    let names: Vec<&str> = calls[0].filters.iter().map(Filter::name).collect();
+    assert_eq!(names, ["All supported", "Images", "Documents"]);
+    assert_eq!(calls[0].default_filter, 0);
+    assert!(calls[0].filters[0].matches("a.png"));
+    assert!(calls[0].filters[0].matches("a.pdf"));
+}
+
+#[test]
+fn validation_reports_only_the_mismatched_paths_in_order() {
+    let (mock, nfd) = setup();
+    mock.push_ok_multiple(vec!["/a.gif", "/b.png", "/c", "/d.TXT", "/e.pdf"]);
+    let res = nfd
+        .open_file_multiple()
+        .add_filters(filters())
+        .unwrap()
+        .validate_selection(true)
+        .show();
+    assert_eq!(
+        res,
+        DialogResult::Err(Error::FilterMismatch(vec![
+            PathBuf::from("/a.gif"),
+            PathBuf::from("/c"),
+            PathBuf::from("/e.pdf"),
+        ]))
+    );
+}
+
+#[test]
+fn validation_passes_without_filters() {
+    let (mock, nfd) = setup();
+    mock.push_ok("/a.gif");
+    mock.push_ok_multiple(vec!["/b", "/c.pdf"]);
+    mock.push_ok("/d");
+    assert_eq!(
+        nfd.open_file()
+            .validate_selection(true)
+            .show()
+            .into_result()
+            .unwrap()
+            .unwrap(),
+        *Path::new("/a.gif")
+    );
+    assert_eq!(
+        paths(
+            nfd.open_file_multiple()
+                .validate_selection(true)
+                .show()
+                .into_result()
+                .unwrap()
+                .unwrap()
+        ),
+        [PathBuf::from("/b"), PathBuf::from("/c.pdf")]
+    );
+    assert_eq!(
+        nfd.save_file()
+            .validate_selection(true)
+            .show()
+            .into_result()
+            .unwrap()
+            .unwrap(),
+        *Path::new("/d")
+    );
+}
+
+#[test]
+fn mismatched_paths_pass_without_validation() {
+    let (mock, nfd) = setup();
+    mock.push_ok("/a.gif");
+    mock.push_ok_multiple(vec!["/b.png", "/c.pdf"]);
+    let mut builder = nfd.open_file();
+    builder
+        .add_filters(filters())
+        .unwrap()
+        .validate_selection(false);
+    assert_eq!(
+        builder.show().into_result().unwrap().unwrap(),
+        *Path::new("/a.gif")
+    );
+    let mut builder = nfd.open_file_multiple();
+    builder
+        .add_filters(filters())
+        .unwrap()
+        .validate_selection(false);
+    assert_eq!(
+        paths(builder.show().into_result().unwrap().unwrap()),
+        [PathBuf::from("/b.png"), PathBuf::from("/c.pdf")]
+    );
+}
+
+#[test]
+fn save_file_validates_the_name_with_the_appended_extension() {
+    let (mock, nfd) = setup();
+    mock.push_ok_with_filter("/home/user/report", 1);
+    // A filter that was not added (e.g. "All files"), so there is no extension to append
+    mock.push_ok_with_filter("/home/user/report", 2);
+    mock.push_ok_with_filter("/home/user/report.png", 1);
+    let mut builder = nfd.save_file();
+    builder
+        .add_filters(filters())
+        .unwrap()
+        .append_extension(true)
+        .validate_selection(true);
+    let selection = builder.show_with_filter().into_result().unwrap().unwrap();
+    assert_eq!(selection.value, *Path::new("/home/user/report.txt"));
+    assert_eq!(selection.filter_index, Some(1));
+    assert_eq!(
+        builder.show(),
+        DialogResult::Err(Error::FilterMismatch(vec![PathBuf::from(
+            "/home/user/report"
+        )]))
+    );
+    // The name already matches one of the filters, so it is kept
+    assert_eq!(
+        builder.show().into_result().unwrap().unwrap(),
+        *Path::new("/home/user/report.png")
+    );
+}
+
+#[test]
+fn builders_without_nfd_use_the_current_backend() {
+    assert_eq!(
+        OpenFileDialogBuilder::new().show(),
+        DialogResult::Err(Error::NotInitialized)
+    );
+    let (mock, _nfd) = setup();
+    mock.push_ok("/a");
+    assert_eq!(
+        OpenFileDialogBuilder::new()
+            .show()
+            .into_result()
+            .unwrap()
+            .unwrap(),
+        *Path::new("/a")
+    );
+}
+
+#[test]
+fn save_file_forbids_existing_files() {
+    let dir = TestDir::new("forbid-existing");
+    let (mock, nfd) = setup();
+    mock.push_ok(dir.join("a.txt"));
+    mock.push_ok(dir.join("b.txt"));
+    let mut builder = nfd.save_file();
+    builder.overwrite_policy(OverwritePolicy::Forbid);
+    assert_eq!(
+        builder.show(),
+        DialogResult::Err(Error::FileExists(dir.join("a.txt")))
+    );
+    assert_eq!(
+        builder.show().into_result().unwrap().unwrap(),
+        dir.join("b.txt")
+    );
+    assert!(mock.calls().iter().all(|call| !call.confirm_overwrite));
+}
+
+#[test]
+fn save_file_confirms_existing_files_with_the_appended_extension() {
+    let dir = TestDir::new("confirm-appended");
+    let (mock, nfd) = setup();
+    // The backend only confirmed "a", so the dialog is shown again with "a.txt"
+    mock.push_ok_with_filter(dir.join("a"), 1);
+    mock.push_ok_with_filter(dir.join("a.txt"), 1);
+    let res = nfd
+        .save_file()
+        .add_filters(filters())
+        .unwrap()
+        .default_filter(1)
+        .append_extension(true)
+        .show_with_filter();
+    let selection = res.into_result().unwrap().unwrap();
+    assert_eq!(selection.value, dir.join("a.txt"));
+    assert_eq!(selection.filter_index, Some(1));
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[1].default_path, Some(dir.0.clone()));
+    assert_eq!(calls[1].default_name, Some(PathBuf::from("a.txt")));
+    assert!(calls[1].confirm_overwrite);
+}
+
+#[test]
+fn save_file_appends_extensions_of_new_files_without_asking_again() {
+    let dir = TestDir::new("append-new");
+    let (mock, nfd) = setup();
+    mock.push_ok(dir.join("b"));
+    let res = nfd
+        .save_file()
+        .add_filters(filters())
+        .unwrap()
+        .default_filter(1)
+        .append_extension(true)
+        .show();
+    assert_eq!(res.into_result().unwrap().unwrap(), dir.join("b.txt"));
+    assert_eq!(mock.calls().len(), 1);
+}
+
+#[test]
+fn save_file_forbids_missing_parents() {
+    let dir = TestDir::new("forbid-missing");
+    let (mock, nfd) = setup();
+    mock.push_ok(dir.join("new/b.txt"));
+    assert_eq!(
+        nfd.save_file()
+            .missing_parent_policy(MissingParentPolicy::Forbid)
+            .show(),
+        DialogResult::Err(Error::MissingParent(dir.join("new")))
+    );
+    assert!(!dir.join("new").exists());
+}
+
+#[test]
+fn save_file_creates_missing_parents() {
+    let dir = TestDir::new("create-missing");
+    let (mock, nfd) = setup();
+    mock.push_ok(dir.join("new/folder/b.txt"));
+    let res = nfd
+        .save_file()
+        .missing_parent_policy(MissingParentPolicy::Create)
+        .show();
+    assert_eq!(
+        res.into_result().unwrap().unwrap(),
+        dir.join("new/folder/b.txt")
+    );
+    assert!(dir.join("new/folder").is_dir());
+}
+
+#[test]
+fn save_file_reports_parents_that_cannot_be_created() {
+    let dir = TestDir::new("create-fails");
+    let (mock, nfd) = setup();
+    // "a.txt" is a file, so no folder can be created in it
+    mock.push_ok(dir.join("a.txt/folder/b.txt"));
+    let res = nfd
+        .save_file()
+        .missing_parent_policy(MissingParentPolicy::Create)
+        .show();
+    match res {
+        DialogResult::Err(Error::CreateParent(parent, _)) => {
+            assert_eq!(parent, dir.join("a.txt/folder"))
+        }
+        res => panic!("unexpected result {:?}", res),
+    }
+}
@@ -0,0 +1,131 @@
+//! Iteration over path sets returned by `MockBackend`.
+//! These do not touch the file system or NFDe, so they also run under Miri (`cargo miri test --test path_set`).
+//! Path sets allocated by NFDe are tested in `src/lib.rs`, against the stand-in for NFDe that Miri runs.
+
+use nfde::backend::mock::MockBackend;
+use nfde::*;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const PATHS: [&str; 3] = ["/a.txt", "/b/c.txt", "/d e.txt"];
+
+fn path_set() -> NfdPathSetBuf {
+    let mock = Arc::new(MockBackend::new());
+    let nfd = Nfd::with_backend(mock.clone()).unwrap();
+    mock.push_ok_multiple(PATHS.iter());
+    nfd.open_file_multiple()
+        .show()
+        .into_result()
+        .unwrap()
+        .unwrap()
+}
+
+fn expected() -> Vec<PathBuf> {
+    PATHS.iter().map(PathBuf::from).collect()
+}
+
+fn collect<I: IntoIterator<Item = Result<NfdPathSetPathBuf, Error>>>(iter: I) -> Vec<PathBuf> {
+    iter.into_iter().map(|path| path.unwrap().into()).collect()
+}
+
+#[test]
+fn iter_yields_every_path_in_order() {
+    let set = path_set();
+    assert_eq!(set.len(), PATHS.len());
+    let mut iter = set.iter();
+    for (index, expected) in PATHS.iter().enumerate() {
+        assert_eq!(iter.len(), PATHS.len() - index);
+        assert_eq!(*iter.next().unwrap().unwrap(), *Path::new(expected));
+    }
+    assert_eq!(iter.len(), 0);
+    assert!(iter.next().is_none());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn iter_can_be_repeated() {
+    let set = path_set();
+    assert_eq!(collect(set.iter()), expected());
+    assert_eq!(collect(&set), expected());
+    assert_eq!(
+        collect(set.iter().rev()),
+        expected().into_iter().rev().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn iter_dropped_early_leaves_the_set_usable() {
+    let set = path_set();
+    {
+        let mut iter = set.iter();
+        assert_eq!(*iter.next().unwrap().unwrap(), *Path::new(PATHS[0]));
+    }
+    assert_eq!(collect(set.iter()), expected());
+    assert_eq!(*set.get(2).unwrap().unwrap(), *Path::new(PATHS[2]));
+}
+
+#[test]
+fn iter_from_both_ends() {
+    let set = path_set();
+    let mut iter = set.iter();
+    assert_eq!(*iter.next_back().unwrap().unwrap(), *Path::new(PATHS[2]));
+    assert_eq!(*iter.next().unwrap().unwrap(), *Path::new(PATHS[0]));
+    assert_eq!(iter.len(), 1);
+    assert_eq!(*iter.next_back().unwrap().unwrap(), *Path::new(PATHS[1]));
+    assert!(iter.next().is_none());
+    assert!(iter.next_back().is_none());
+}
+
+#[test]
+fn iter_nth() {
+    let set = path_set();
+    let mut iter = set.iter();
+    assert_eq!(*iter.nth(1).unwrap().unwrap(), *Path::new(PATHS[1]));
+    assert_eq!(iter.len(), 1);
+    assert!(iter.nth(1).is_none());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn into_iter_yields_every_path_in_order() {
+    let mut iter = path_set().into_iter();
+    assert_eq!(iter.len(), PATHS.len());
+    assert_eq!(*iter.nth(1).unwrap().unwrap(), *Path::new(PATHS[1]));
+    assert_eq!(collect(iter), expected()[2..]);
+    assert_eq!(
+        collect(path_set().into_iter().rev()),
+        expected().into_iter().rev().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn into_iter_dropped_early() {
+    // Neither the set nor the paths already taken from it may be freed twice or leaked
+    let mut iter = path_set().into_iter();
+    let first = iter.next().unwrap().unwrap();
+    drop(iter);
+    assert_eq!(*first, *Path::new(PATHS[0]));
+    drop(path_set().into_iter());
+}
+
+#[test]
+fn paths_outlive_the_set() {
+    let set = path_set();
+    let paths: Vec<NfdPathSetPathBuf> = set.iter().map(Result::unwrap).collect();
+    let second = set.get(1).unwrap().unwrap();
+    drop(set);
+    assert_eq!(*second, *Path::new(PATHS[1]));
+    assert_eq!(
+        paths.into_iter().map(PathBuf::from).collect::<Vec<_>>(),
+        expected()
+    );
+}
+
+#[test]
+fn get_out_of_range() {
+    let set = path_set();
+    assert!(set.get(PATHS.len()).is_none());
+    assert!(set.get(usize::MAX).is_none());
+    assert_eq!(set.into_paths().unwrap(), expected());
+}
@@ -0,0 +1,519 @@
+//! Shows dialogs through a stand-in for the file chooser portal, which records each request
+//! and answers with a scripted selection.
+//! The `xdg-portal` backend is tested with the `xdg-portal` feature, and NFDe with the `portal` feature.
+//! The stand-in needs a private session bus, so these tests are ignored by default; run them with
+//! `dbus-run-session -- cargo test --features xdg-portal --test portal -- --include-ignored`.
+#![cfg(all(target_os = "linux", any(feature = "xdg-portal", feature = "portal")))]
+
+mod common;
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::OnceLock;
+use std::sync::PoisonError;
+use std::time::Duration;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::zvariant::OwnedValue;
+use zbus::zvariant::Value;
+
+type PortalFilter = (String, Vec<(u32, String)>);
+
+/// A call to the stand-in portal.
+struct Request {
+    method: String,
+    parent_window: String,
+    title: String,
+    options: HashMap<String, OwnedValue>,
+}
+
+impl Request {
+    fn option<T: TryFrom<Value<'static>>>(&self, key: &str) -> Option<T> {
+        let value = self.options.get(key)?.try_clone().unwrap();
+        T::try_from(Value::from(value)).ok()
+    }
+
+    fn filters(&self) -> Vec<PortalFilter> {
+        self.option("filters").unwrap_or_default()
+    }
+}
+
+/// How the stand-in answers the next request.
+enum Response {
+    /// Selects the URIs, and the filter at this index of the filters of the request.
+    Select(Vec<&'static str>, Option<usize>),
+    Cancel,
+}
+
+#[derive(Default)]
+struct State {
+    requests: Vec<Request>,
+    responses: VecDeque<Response>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    requests: Vec::new(),
+    responses: VecDeque::new(),
+});
+
+fn state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+struct FileChooser;
+
+#[zbus::interface(name = "org.freedesktop.portal.FileChooser")]
+impl FileChooser {
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        4
+    }
+
+    fn open_file(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &zbus::Connection,
+        parent_window: String,
+        title: String,
+        options: HashMap<String, OwnedValue>,
+    ) -> OwnedObjectPath {
+        respond(
+            header,
+            connection,
+            "OpenFile",
+            parent_window,
+            title,
+            options,
+        )
+    }
+
+    fn save_file(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &zbus::Connection,
+        parent_window: String,
+        title: String,
+        options: HashMap<String, OwnedValue>,
+    ) -> OwnedObjectPath {
+        respond(
+            header,
+            connection,
+            "SaveFile",
+            parent_window,
+            title,
+            options,
+        )
+    }
+}
+
+/// Records the request, and sends the `Response` signal of the request object shortly after replying with its path.
+fn respond(
+    header: Header<'_>,
+    connection: &zbus::Connection,
+    method: &str,
+    parent_window: String,
+    title: String,
+    options: HashMap<String, OwnedValue>,
+) -> OwnedObjectPath {
+    let sender = header
+        .sender()
+        .unwrap()
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let token = String::try_from(options["handle_token"].try_clone().unwrap()).unwrap();
+    let path = format!(
+        "/org/freedesktop/portal/desktop/request/{}/{}",
+        sender, token
+    );
+    let request = Request {
+        method: method.to_string(),
+        parent_window,
+        title,
+        options,
+    };
+    let mut state = state();
+    let (response, results) = match state.responses.pop_front() {
+        Some(Response::Select(uris, filter)) => {
+            let mut results: HashMap<&str, Value<'static>> = HashMap::new();
+            results.insert("uris", Value::from(uris));
+            if let Some(filter) = filter {
+                results.insert(
+                    "current_filter",
+                    Value::from(request.filters()[filter].clone()),
+                );
+            }
+            (0u32, results)
+        }
+        Some(Response::Cancel) => (1, HashMap::new()),
+        None => (2, HashMap::new()),
+    };
+    state.requests.push(request);
+    let connection = connection.clone();
+    let request_path = path.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        zbus::block_on(async {
+            SignalEmitter::new(&connection, request_path.as_str())
+                .unwrap()
+                .emit(
+                    "org.freedesktop.portal.Request",
+                    "Response",
+                    &(response, results),
+                )
+                .await
+                .unwrap();
+        });
+    });
+    OwnedObjectPath::try_from(path).unwrap()
+}
+
+// The portal tests share the stand-in, so only one of them may run at a time
+static LOCK: Mutex<()> = Mutex::new(());
+static PORTAL: OnceLock<zbus::blocking::Connection> = OnceLock::new();
+
+/// Starts the stand-in portal.  Panics if there is no session bus to run it on.
+/// The portal is reserved for the caller until the guard is dropped.
+fn portal() -> MutexGuard<'static, ()> {
+    assert!(
+        std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some(),
+        "No session bus for the stand-in portal (run the tests with dbus-run-session)"
+    );
+    let lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    PORTAL.get_or_init(|| {
+        zbus::blocking::connection::Builder::session()
+            .unwrap()
+            .name("org.freedesktop.portal.Desktop")
+            .unwrap()
+            .serve_at("/org/freedesktop/portal/desktop", FileChooser)
+            .unwrap()
+            .build()
+            .unwrap()
+    });
+    *state() = State::default();
+    lock
+}
+
+fn respond_with(response: Response) {
+    state().responses.push_back(response);
+}
+
+fn last_request() -> Request {
+    state().requests.pop().unwrap()
+}
+
+#[cfg(feature = "xdg-portal")]
+mod xdg_portal {
+    use super::common::filters;
+    use super::*;
+    use nfde::backend::portal::PortalBackend;
+    use nfde::*;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    fn nfd() -> Nfd {
+        Nfd::with_backend(Arc::new(PortalBackend::new())).unwrap()
+    }
+
+    fn portal_filters() -> Vec<PortalFilter> {
+        vec![
+            (
+                String::from("Images"),
+                vec![(0, String::from("*.png")), (0, String::from("*.jpg"))],
+            ),
+            (String::from("Text"), vec![(0, String::from("*.txt"))]),
+        ]
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn open_file() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(
+            vec!["file:///home/user/a%20b.txt"],
+            Some(1),
+        ));
+        let selection = nfd
+            .open_file()
+            .add_filters(filters())
+            .unwrap()
+            .default_filter(1)
+            .default_path("/home/user")
+            .unwrap()
+            .show_with_filter()
+            .into_result()
+            .unwrap()
+            .unwrap();
+        assert_eq!(selection.value, *Path::new("/home/user/a b.txt"));
+        assert_eq!(selection.filter_index, Some(1));
+        let request = last_request();
+        assert_eq!(request.method, "OpenFile");
+        assert_eq!(request.title, "Open File");
+        assert_eq!(request.parent_window, "");
+        assert_eq!(request.option::<bool>("modal"), Some(true));
+        assert_eq!(request.option::<bool>("multiple"), None);
+        assert_eq!(
+            request.option::<Vec<u8>>("current_folder"),
+            Some(b"/home/user\0".to_vec())
+        );
+        assert_eq!(request.filters(), portal_filters());
+        assert_eq!(
+            request.option::<PortalFilter>("current_filter"),
+            Some(portal_filters()[1].clone())
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn open_file_multiple() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(
+            vec!["file:///a.txt", "file://localhost/b.txt"],
+            None,
+        ));
+        let selection = nfd
+            .open_file_multiple()
+            .add_filters(filters())
+            .unwrap()
+            .show_with_filter()
+            .into_result()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            selection.value.into_paths().unwrap(),
+            [PathBuf::from("/a.txt"), PathBuf::from("/b.txt")]
+        );
+        // Without a current filter in the response, it is guessed from the extensions
+        assert_eq!(selection.filter_index, Some(1));
+        let request = last_request();
+        assert_eq!(request.title, "Open Files");
+        assert_eq!(request.option::<bool>("multiple"), Some(true));
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn save_file() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(vec!["file:///nonexistent/out.txt"], None));
+        let path = nfd
+            .save_file()
+            .default_name(&"out.txt")
+            .unwrap()
+            .show()
+            .into_result()
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, *Path::new("/nonexistent/out.txt"));
+        let request = last_request();
+        assert_eq!(request.method, "SaveFile");
+        assert_eq!(request.title, "Save File");
+        assert_eq!(
+            request.option::<String>("current_name"),
+            Some(String::from("out.txt"))
+        );
+        assert!(request.filters().is_empty());
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn pick_folders() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(vec!["file:///home"], None));
+        respond_with(Response::Select(vec!["file:///home", "file:///tmp"], None));
+        assert_eq!(
+            nfd.pick_folder().show().into_result().unwrap().unwrap(),
+            *Path::new("/home")
+        );
+        let request = last_request();
+        assert_eq!(request.title, "Select Folder");
+        assert_eq!(request.option::<bool>("directory"), Some(true));
+        let paths = nfd
+            .pick_folder_multiple()
+            .show()
+            .into_result()
+            .unwrap()
+            .unwrap()
+            .into_paths()
+            .unwrap();
+        assert_eq!(paths, [PathBuf::from("/home"), PathBuf::from("/tmp")]);
+        let request = last_request();
+        assert_eq!(request.option::<bool>("directory"), Some(true));
+        assert_eq!(request.option::<bool>("multiple"), Some(true));
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn mime_type_filters_are_passed_through() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(vec!["file:///a.png"], Some(0)));
+        let selection = nfd
+            .open_file()
+            .add_filter(Filter::from_mime_types("PNG", &["image/png"]).unwrap())
+            .unwrap()
+            .show_with_filter()
+            .into_result()
+            .unwrap()
+            .unwrap();
+        assert_eq!(selection.filter_index, Some(0));
+        assert_eq!(
+            last_request().filters(),
+            [(String::from("PNG"), vec![(1, String::from("image/png"))])]
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn cancel_and_failure() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Cancel);
+        assert_eq!(nfd.open_file().show(), DialogResult::Cancel);
+        // The stand-in ends the interaction when it has no response
+        assert!(matches!(
+            nfd.open_file().show(),
+            DialogResult::Err(Error::Dialog(_))
+        ));
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn only_local_uris_are_accepted() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(
+            vec!["file://localhost/home/user/a%20b.txt"],
+            None,
+        ));
+        respond_with(Response::Select(
+            vec!["file://otherhost/home/user/a.txt"],
+            None,
+        ));
+        respond_with(Response::Select(vec!["https://example.com/a.txt"], None));
+        assert_eq!(
+            nfd.open_file().show().into_result().unwrap().unwrap(),
+            *Path::new("/home/user/a b.txt")
+        );
+        for _ in 0..2 {
+            assert!(matches!(
+                nfd.open_file().show(),
+                DialogResult::Err(Error::Dialog(_))
+            ));
+        }
+    }
+}
+
+// NFDe builds its own portal options, so only the parts of the requests that every portal needs are checked
+#[cfg(all(feature = "portal", not(feature = "xdg-portal")))]
+mod native {
+    use super::*;
+    use nfde::backend::BackendKind;
+    use nfde::*;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    fn nfd() -> Nfd {
+        Nfd::builder()
+            .backends([BackendKind::Native])
+            .init()
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn open_file() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(vec!["file:///home/user/a.txt"], None));
+        let path = nfd
+            .open_file()
+            .add_filter(("Text", "txt"))
+            .unwrap()
+            .show()
+            .into_result()
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, *Path::new("/home/user/a.txt"));
+        let request = last_request();
+        assert_eq!(request.method, "OpenFile");
+        assert_eq!(request.title, "Open File");
+        assert_eq!(request.parent_window, "");
+        assert_ne!(request.option::<bool>("multiple"), Some(true));
+        assert_eq!(request.filters()[0].0, "Text");
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn open_file_multiple() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(
+            vec!["file:///a.txt", "file:///b.txt"],
+            None,
+        ));
+        let paths = nfd
+            .open_file_multiple()
+            .show()
+            .into_result()
+            .unwrap()
+            .unwrap()
+            .into_paths()
+            .unwrap();
+        assert_eq!(paths, [PathBuf::from("/a.txt"), PathBuf::from("/b.txt")]);
+        let request = last_request();
+        assert_eq!(request.method, "OpenFile");
+        assert_eq!(request.option::<bool>("multiple"), Some(true));
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn save_file() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(vec!["file:///nonexistent/out.txt"], None));
+        let path = nfd
+            .save_file()
+            .default_name(&"out.txt")
+            .unwrap()
+            .show()
+            .into_result()
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, *Path::new("/nonexistent/out.txt"));
+        let request = last_request();
+        assert_eq!(request.method, "SaveFile");
+        assert_eq!(request.title, "Save File");
+        assert_eq!(
+            request.option::<String>("current_name"),
+            Some(String::from("out.txt"))
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn pick_folder() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Select(vec!["file:///home"], None));
+        let path = nfd.pick_folder().show().into_result().unwrap().unwrap();
+        assert_eq!(path, *Path::new("/home"));
+        assert_eq!(last_request().option::<bool>("directory"), Some(true));
+    }
+
+    #[test]
+    #[ignore = "needs a private session bus (run with dbus-run-session and --include-ignored)"]
+    fn cancel() {
+        let _portal = portal();
+        let nfd = nfd();
+        respond_with(Response::Cancel);
+        assert_eq!(nfd.open_file().show(), DialogResult::Cancel);
+    }
+}
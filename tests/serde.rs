@@ -0,0 +1,163 @@
+//! Checks that the serializable types come back unchanged from JSON, and that invalid filters are rejected.
+//! Only built with the `serde` feature.
+
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::filters;
+use common::setup;
+use nfde::backend::mock::MockResponse;
+use nfde::backend::BackendKind;
+use nfde::backend::DialogKind;
+use nfde::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let json = serde_json::to_string(value).unwrap();
+    let back: T = serde_json::from_str(&json).unwrap();
+    assert_eq!(&back, value, "{}", json);
+}
+
+#[test]
+fn dialog_config() {
+    let (_mock, nfd) = setup();
+    let mut builder = nfd.save_file();
+    builder
+        .add_filters(filters())
+        .unwrap()
+        .add_filter(Filter::from_mime_types("Text", &["text/plain"]).unwrap())
+        .unwrap()
+        .default_filter(2)
+        .default_path("/home/user")
+        .unwrap()
+        .default_name(&"out.txt")
+        .unwrap();
+    let config = builder.config();
+    round_trip(&config);
+
+    let json = serde_json::to_string(&config).unwrap();
+    let mut other = nfd.save_file();
+    other
+        .apply_config(&serde_json::from_str(&json).unwrap())
+        .unwrap();
+    assert_eq!(other.config(), config);
+
+    round_trip(&DialogConfig {
+        kind: DialogKind::PickFolder,
+        filters: Vec::new(),
+        default_filter: 0,
+        default_path: None,
+        default_name: None,
+    });
+}
+
+#[test]
+fn errors() {
+    let errors = vec![
+        Error::InvalidFilter(FilterError::ExtensionWildcard),
+        Error::InvalidFilter(FilterError::MixedSpec),
+        Error::InteriorNul,
+        Error::InvalidParentWindow,
+        Error::Init(String::from("no display")),
+        Error::NoBackend(vec![
+            (BackendKind::Native, Error::Init(String::from("no display"))),
+            (BackendKind::Tui, Error::WrongThread),
+        ]),
+        Error::NotInitialized,
+        Error::WrongThread,
+        Error::FilterMismatch(vec![PathBuf::from("/a.gif"), PathBuf::from("/b")]),
+        Error::FileExists(PathBuf::from("/a.txt")),
+        Error::MissingParent(PathBuf::from("/new")),
+        Error::CreateParent(PathBuf::from("/new"), String::from("permission denied")),
+        Error::Dialog(String::from("scripted failure")),
+        Error::PathSetEnum(String::from("scripted failure")),
+    ];
+    for error in &errors {
+        round_trip(error);
+    }
+    round_trip(&errors);
+}
+
+#[test]
+fn unknown_filter_errors_are_rejected() {
+    assert!(serde_json::from_str::<Error>(r#"{"InvalidFilter":"NotAReason"}"#).is_err());
+    assert!(
+        serde_json::from_str::<Error>(r#"{"InvalidFilter":"Filter has no extensions"}"#).is_err()
+    );
+}
+
+#[test]
+fn single_selections() {
+    let (mock, nfd) = setup();
+    mock.push_ok_with_filter("/home/user/a.txt", 1);
+    mock.push_cancel();
+    let mut builder = nfd.open_file();
+    builder.add_filters(filters()).unwrap();
+    let selection = builder.show_with_filter();
+    assert!(selection.clone().into_result().unwrap().is_some());
+    round_trip(&selection);
+    round_trip(&builder.show_with_filter());
+    round_trip(&SingleFileResult::Err(Error::NotInitialized));
+
+    let path = NfdPathBuf::from(PathBuf::from("/home/user/a b.txt"));
+    round_trip(&path);
+    assert_eq!(
+        serde_json::to_string(&path).unwrap(),
+        r#""/home/user/a b.txt""#
+    );
+}
+
+#[test]
+fn multiple_selections() {
+    let (mock, nfd) = setup();
+    mock.push_response(MockResponse::OkWithFilter(
+        vec![PathBuf::from("/a.png"), PathBuf::from("/b.jpg")],
+        0,
+    ));
+    mock.push_ok_multiple(vec!["/c.txt"]);
+    let mut builder = nfd.open_file_multiple();
+    builder.add_filters(filters()).unwrap();
+    let selection = builder.show_with_filter();
+    round_trip(&selection);
+    assert_eq!(
+        serde_json::to_string(&selection).unwrap(),
+        r#"{"Ok":{"value":["/a.png","/b.jpg"],"filter_index":0}}"#
+    );
+    round_trip(&builder.show());
+    round_trip(&MultipleFileSelection::Cancel);
+}
+
+#[test]
+fn invalid_filters_are_rejected() {
+    let valid = r#"{"name":"Images","extensions":["png","jpg"],"mime_types":["image/png"]}"#;
+    let filter: Filter = serde_json::from_str(valid).unwrap();
+    assert_eq!(filter.extensions(), ["png", "jpg"]);
+    assert_eq!(filter.mime_types(), ["image/png"]);
+    round_trip(&filter);
+
+    for invalid in [
+        r#"{"name":"Images","extensions":["*.png"],"mime_types":[]}"#,
+        r#"{"name":"Images","extensions":[".png"],"mime_types":[]}"#,
+        r#"{"name":"Images","extensions":[],"mime_types":[]}"#,
+        r#"{"name":"Images","extensions":["png"],"mime_types":["image"]}"#,
+        r#"{"name":"Ima\u0000ges","extensions":["png"],"mime_types":[]}"#,
+    ]
+    .iter()
+    {
+        assert!(
+            serde_json::from_str::<Filter>(invalid).is_err(),
+            "{}",
+            invalid
+        );
+    }
+    // Configurations with invalid filters are rejected too
+    assert!(serde_json::from_str::<DialogConfig>(
+        r#"{"kind":"OpenFile","filters":[{"name":"Images","extensions":["png;jpg"],"mime_types":[]}],
+            "default_filter":0,"default_path":null,"default_name":null}"#
+    )
+    .is_err());
+}
@@ -0,0 +1,292 @@
+//! Runs `SubprocessBackend` against fake `zenity` and `kdialog` scripts on `PATH`,
+//! which record their arguments and print a scripted selection.
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::filters;
+use nfde::backend::subprocess::SubprocessBackend;
+use nfde::backend::subprocess::Tool;
+use nfde::*;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::Once;
+use std::sync::PoisonError;
+
+// Records its arguments (separated by nul characters, since filters contain newlines),
+// then prints the scripted output and exits with the scripted status.
+const FAKE_TOOL: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+printf '%s\0' "$@" > "$dir/args"
+cat "$dir/stdout"
+exit "$(cat "$dir/status")"
+"#;
+
+// The fake tools share one directory, so only one test may use them at a time
+static LOCK: Mutex<()> = Mutex::new(());
+static SETUP: Once = Once::new();
+
+fn fake_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("nfde-fake-tools-{}", std::process::id()))
+}
+
+/// The fake tools, scripted to print `stdout` and exit with `status`.
+struct FakeTools {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl FakeTools {
+    fn new(stdout: &str, status: i32) -> Self {
+        let lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = fake_dir();
+        SETUP.call_once(|| {
+            fs::create_dir_all(&dir).unwrap();
+            for tool in [Tool::Zenity, Tool::KDialog].iter() {
+                let program = dir.join(tool.program());
+                fs::write(&program, FAKE_TOOL).unwrap();
+                fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+            let mut paths = vec![dir.clone()];
+            paths.extend(std::env::split_paths(
+                &std::env::var_os("PATH").unwrap_or_default(),
+            ));
+            std::env::set_var("PATH", std::env::join_paths(paths).unwrap());
+            // The backend refuses to start without a display
+            if std::env::var_os("DISPLAY").is_none() {
+                std::env::set_var("DISPLAY", ":0");
+            }
+        });
+        fs::write(dir.join("stdout"), stdout).unwrap();
+        fs::write(dir.join("status"), status.to_string()).unwrap();
+        let _ = fs::remove_file(dir.join("args"));
+        Self { _lock: lock }
+    }
+
+    /// The arguments of the last run of either tool.
+    fn args(&self) -> Vec<String> {
+        let args = fs::read(fake_dir().join("args")).unwrap();
+        String::from_utf8(args)
+            .unwrap()
+            .split_terminator('\0')
+            .map(String::from)
+            .collect()
+    }
+}
+
+fn nfd(tool: Tool) -> Nfd {
+    Nfd::with_backend(Arc::new(SubprocessBackend::new(tool))).unwrap()
+}
+
+#[test]
+fn detect_finds_zenity_first() {
+    let _tools = FakeTools::new("", 1);
+    assert_eq!(SubprocessBackend::detect().unwrap().tool(), Tool::Zenity);
+}
+
+#[test]
+fn zenity_open_file() {
+    let tools = FakeTools::new("/home/user/notes.txt\n", 0);
+    let selection = nfd(Tool::Zenity)
+        .open_file()
+        .add_filters(filters())
+        .unwrap()
+        .default_filter(1)
+        .default_path("/home/user")
+        .unwrap()
+        .show_with_filter()
+        .into_result()
+        .unwrap()
+        .unwrap();
+    assert_eq!(selection.value, *Path::new("/home/user/notes.txt"));
+    assert_eq!(selection.filter_index, Some(1));
+    assert_eq!(
+        tools.args(),
+        [
+            "--file-selection",
+            "--title=Open File",
+            "--separator=\n",
+            "--filename=/home/user/",
+            // The default filter is moved to the front, since zenity selects the first filter
+            "--file-filter=Text | *.txt",
+            "--file-filter=Images | *.png *.jpg",
+        ]
+    );
+}
+
+#[test]
+fn zenity_open_file_multiple() {
+    let tools = FakeTools::new("/a b.png\n/c.jpg\n", 0);
+    let paths = nfd(Tool::Zenity)
+        .open_file_multiple()
+        .show()
+        .into_result()
+        .unwrap()
+        .unwrap()
+        .into_paths()
+        .unwrap();
+    assert_eq!(paths, [PathBuf::from("/a b.png"), PathBuf::from("/c.jpg")]);
+    assert!(tools.args().contains(&String::from("--multiple")));
+}
+
+#[test]
+fn zenity_save_file() {
+    let tools = FakeTools::new("/nonexistent/report.txt\n", 0);
+    let path = nfd(Tool::Zenity)
+        .save_file()
+        .default_path("/nonexistent")
+        .unwrap()
+        .default_name(&"report.txt")
+        .unwrap()
+        .show()
+        .into_result()
+        .unwrap()
+        .unwrap();
+    assert_eq!(path, *Path::new("/nonexistent/report.txt"));
+    let args = tools.args();
+    assert!(args.contains(&String::from("--save")));
+    assert!(args.contains(&String::from("--confirm-overwrite")));
+    assert!(args.contains(&String::from("--filename=/nonexistent/report.txt")));
+}
+
+#[test]
+fn zenity_save_file_without_confirmation() {
+    let tools = FakeTools::new("/nonexistent/report.txt\n", 0);
+    nfd(Tool::Zenity)
+        .save_file()
+        .overwrite_policy(OverwritePolicy::Allow)
+        .show()
+        .into_result()
+        .unwrap()
+        .unwrap();
+    assert!(!tools.args().contains(&String::from("--confirm-overwrite")));
+}
+
+#[test]
+fn zenity_cancel_and_failure() {
+    let nfd = nfd(Tool::Zenity);
+    {
+        let _tools = FakeTools::new("", 1);
+        assert_eq!(nfd.pick_folder().show(), DialogResult::Cancel);
+    }
+    {
+        let _tools = FakeTools::new("", 5);
+        assert!(matches!(
+            nfd.pick_folder().show(),
+            DialogResult::Err(Error::Dialog(_))
+        ));
+    }
+    {
+        let _tools = FakeTools::new("", 0);
+        assert!(matches!(
+            nfd.pick_folder().show(),
+            DialogResult::Err(Error::Dialog(_))
+        ));
+    }
+}
+
+#[test]
+fn zenity_rejects_several_paths_for_single_dialogs() {
+    let _tools = FakeTools::new("/a\n/b\n", 0);
+    assert!(matches!(
+        nfd(Tool::Zenity).open_file().show(),
+        DialogResult::Err(Error::Dialog(_))
+    ));
+}
+
+#[test]
+fn kdialog_open_file() {
+    let tools = FakeTools::new("/home/user/a.png\n", 0);
+    let selection = nfd(Tool::KDialog)
+        .open_file()
+        .add_filters(filters())
+        .unwrap()
+        .show_with_filter()
+        .into_result()
+        .unwrap()
+        .unwrap();
+    assert_eq!(selection.value, *Path::new("/home/user/a.png"));
+    assert_eq!(selection.filter_index, Some(0));
+    assert_eq!(
+        tools.args(),
+        [
+            "--title",
+            "Open File",
+            "--getopenfilename",
+            // The start path is positional, so it is needed before the filters
+            ".",
+            "*.png *.jpg|Images\n*.txt|Text",
+        ]
+    );
+}
+
+#[test]
+fn kdialog_open_file_multiple() {
+    let tools = FakeTools::new("/a\n/b\n", 0);
+    let paths = nfd(Tool::KDialog)
+        .open_file_multiple()
+        .default_path("/home")
+        .unwrap()
+        .show()
+        .into_result()
+        .unwrap()
+        .unwrap()
+        .into_paths()
+        .unwrap();
+    assert_eq!(paths, [PathBuf::from("/a"), PathBuf::from("/b")]);
+    assert_eq!(
+        tools.args(),
+        [
+            "--title",
+            "Open Files",
+            "--getopenfilename",
+            "--multiple",
+            "--separate-output",
+            "/home",
+        ]
+    );
+}
+
+#[test]
+fn kdialog_save_file() {
+    let tools = FakeTools::new("/nonexistent/out.txt\n", 0);
+    nfd(Tool::KDialog)
+        .save_file()
+        .default_path("/nonexistent")
+        .unwrap()
+        .default_name(&"out.txt")
+        .unwrap()
+        .show()
+        .into_result()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        tools.args(),
+        [
+            "--title",
+            "Save File",
+            "--getsavefilename",
+            "/nonexistent/out.txt",
+        ]
+    );
+}
+
+#[test]
+fn kdialog_pick_folder() {
+    let tools = FakeTools::new("/home/user\n", 0);
+    let path = nfd(Tool::KDialog)
+        .pick_folder()
+        .show()
+        .into_result()
+        .unwrap()
+        .unwrap();
+    assert_eq!(path, *Path::new("/home/user"));
+    assert_eq!(
+        tools.args(),
+        ["--title", "Select Folder", "--getexistingdirectory"]
+    );
+}
@@ -0,0 +1,251 @@
+//! Shows dialogs of the terminal backend in a pseudo-terminal, and feeds them scripted key presses.
+//! Each dialog runs in a child process (this test binary again, running only `tui_child`),
+//! whose stdin and stderr are the pseudo-terminal.
+#![cfg(all(target_os = "linux", feature = "tui"))]
+
+use nfde::backend::tui::TuiBackend;
+use nfde::*;
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+const SCENARIO_VAR: &str = "NFDE_TUI_TEST_SCENARIO";
+const DIR_VAR: &str = "NFDE_TUI_TEST_DIR";
+
+const DOWN: &[u8] = b"\x1b[B";
+const ENTER: &[u8] = b"\r";
+const ESC: &[u8] = b"\x1b";
+const SPACE: &[u8] = b" ";
+const TAB: &[u8] = b"\t";
+
+/// Shows the dialog of the scenario, and prints its result for `run` to read.
+/// Does nothing unless it is started by `run`.
+#[test]
+fn tui_child() {
+    let scenario = match std::env::var(SCENARIO_VAR) {
+        Ok(scenario) => scenario,
+        Err(_) => return,
+    };
+    let dir = PathBuf::from(std::env::var_os(DIR_VAR).unwrap());
+    let nfd = Nfd::with_backend(Arc::new(TuiBackend::new())).unwrap();
+    let filters = vec![
+        Filter::new("Text", &["txt"]).unwrap(),
+        Filter::new("Rust", &["rs"]).unwrap(),
+    ];
+    let result = match scenario.as_str() {
+        "open_file" => nfd
+            .open_file()
+            .add_filters(filters.clone())
+            .unwrap()
+            .default_path(&dir)
+            .unwrap()
+            .show_with_filter()
+            .map(|selection| (vec![selection.value.to_path_buf()], selection.filter_index)),
+        "open_file_multiple" => nfd
+            .open_file_multiple()
+            .add_filters(filters.clone())
+            .unwrap()
+            .default_path(&dir)
+            .unwrap()
+            .show_with_filter()
+            .map(|selection| {
+                (
+                    selection.value.into_paths().unwrap(),
+                    selection.filter_index,
+                )
+            }),
+        "save_file" => nfd
+            .save_file()
+            .default_path(&dir)
+            .unwrap()
+            .default_name(&"a.txt")
+            .unwrap()
+            .show_with_filter()
+            .map(|selection| (vec![selection.value.to_path_buf()], selection.filter_index)),
+        "pick_folder" => nfd
+            .pick_folder()
+            .default_path(&dir)
+            .unwrap()
+            .show()
+            .map(|path| (vec![path.to_path_buf()], None)),
+        _ => panic!("unknown scenario {}", scenario),
+    };
+    println!("RESULT {:?}", result);
+}
+
+/// A folder with a subfolder and a few files, which is removed when dropped.
+struct TestDir(PathBuf);
+
+impl TestDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("nfde-tui-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("folder")).unwrap();
+        for file in ["a.txt", "b.txt", "c.rs"].iter() {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        // The backend lists the canonical path
+        TestDir(dir.canonicalize().unwrap())
+    }
+
+    fn join(&self, path: &str) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn open_pty() -> (File, File) {
+    let mut master = 0;
+    let mut slave = 0;
+    let size = libc::winsize {
+        ws_row: 24,
+        ws_col: 80,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let res = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            &size,
+        )
+    };
+    assert_eq!(res, 0, "openpty failed");
+    unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) }
+}
+
+/// Runs the scenario in `tui_child` in a pseudo-terminal, presses the keys once the dialog is shown,
+/// and returns the result that it printed.
+fn run(scenario: &str, dir: &Path, keys: &[&[u8]]) -> String {
+    let (mut master, slave) = open_pty();
+    let child = {
+        let mut command = Command::new(std::env::current_exe().unwrap());
+        command
+            .args(["--exact", "tui_child", "--nocapture", "--test-threads=1"])
+            .env(SCENARIO_VAR, scenario)
+            .env(DIR_VAR, dir)
+            .stdin(slave.try_clone().unwrap())
+            .stderr(slave)
+            .stdout(Stdio::piped());
+        // Make the pseudo-terminal the controlling terminal of the child, like a shell would
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        command.spawn().unwrap()
+        // The slave is closed here, so reading from the master fails once the child exits
+    };
+
+    // The screen must be read continuously, or the child blocks when the pseudo-terminal is full
+    let (screen_tx, screen_rx) = mpsc::channel();
+    let mut reader = master.try_clone().unwrap();
+    std::thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        while let Ok(len @ 1..=usize::MAX) = reader.read(&mut buffer) {
+            if screen_tx.send(buffer[..len].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    // The alternate screen is entered after raw mode, so keys pressed from then on are read one by one
+    let mut screen = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !String::from_utf8_lossy(&screen).contains("\x1b[?1049h") {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        screen.extend(
+            screen_rx
+                .recv_timeout(timeout)
+                .expect("the dialog was not shown"),
+        );
+    }
+    for key in keys {
+        master.write_all(key).unwrap();
+        // Keep a lone Esc from being read as the start of an escape sequence
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout
+        .lines()
+        // The test harness prints the name of the test on the same line
+        .find_map(|line| line.split_once("RESULT ").map(|(_, result)| result))
+        .unwrap_or_else(|| panic!("no result in {:?}", stdout))
+        .to_string()
+}
+
+fn ok(paths: &[PathBuf], filter_index: Option<usize>) -> String {
+    format!("{:?}", DialogResult::Ok((paths.to_vec(), filter_index)))
+}
+
+#[test]
+fn open_file_chooses_a_matching_file() {
+    let dir = TestDir::new("open");
+    // "..", "folder", "a.txt", "b.txt"; c.rs does not match the filter
+    let result = run("open_file", &dir.0, &[DOWN, DOWN, DOWN, ENTER]);
+    assert_eq!(result, ok(&[dir.join("b.txt")], Some(0)));
+}
+
+#[test]
+fn open_file_switches_filters_with_tab() {
+    let dir = TestDir::new("filter");
+    // "..", "folder", "c.rs"
+    let result = run("open_file", &dir.0, &[TAB, DOWN, DOWN, ENTER]);
+    assert_eq!(result, ok(&[dir.join("c.rs")], Some(1)));
+}
+
+#[test]
+fn open_file_multiple_chooses_marked_files() {
+    let dir = TestDir::new("multiple");
+    let result = run(
+        "open_file_multiple",
+        &dir.0,
+        &[DOWN, DOWN, SPACE, SPACE, ENTER],
+    );
+    assert_eq!(result, ok(&[dir.join("a.txt"), dir.join("b.txt")], Some(0)));
+}
+
+#[test]
+fn save_file_confirms_overwriting() {
+    let dir = TestDir::new("save");
+    // The default name exists, so the first Enter only asks for confirmation
+    let result = run("save_file", &dir.0, &[ENTER, ENTER]);
+    assert_eq!(result, ok(&[dir.join("a.txt")], None));
+}
+
+#[test]
+fn pick_folder_chooses_a_subfolder() {
+    let dir = TestDir::new("folder");
+    // ".", "..", "folder"; Enter opens the folder, then chooses its "."
+    let result = run("pick_folder", &dir.0, &[DOWN, DOWN, ENTER, ENTER]);
+    assert_eq!(result, ok(&[dir.join("folder")], None));
+}
+
+#[test]
+fn escape_cancels() {
+    let dir = TestDir::new("cancel");
+    let result = run("open_file", &dir.0, &[ESC]);
+    assert_eq!(result, format!("{:?}", DialogResult::<()>::Cancel));
+}